@@ -0,0 +1,47 @@
+use super::{vec3_ui, Attribute};
+
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Attractor {
+    pub position: [f32; 3],
+    // negative values turn the attractor into a repulsor.
+    pub strength: f32,
+    // particles farther than the radius are not affected.
+    pub radius: f32,
+    // exponent of the falloff, 0 is constant and 1 linear.
+    pub falloff: f32,
+    _pad: [f32; 2],
+}
+
+impl Attractor {
+    pub fn new(position: [f32; 3], strength: f32, radius: f32, falloff: f32) -> Self {
+        Self {
+            position,
+            strength,
+            radius,
+            falloff,
+            _pad: [0.0; 2],
+        }
+    }
+}
+
+impl Attribute for Attractor {
+    fn name(&self) -> &'static str {
+        "attractor"
+    }
+
+    fn source(&self) -> &'static str {
+        include_str!("../shaders/modules/attractor.wgsl")
+    }
+
+    fn params(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        vec3_ui(ui, "position", &mut self.position);
+        ui.add(egui::Slider::new(&mut self.strength, -100.0..=100.0).text("strength"));
+        ui.add(egui::Slider::new(&mut self.radius, 0.1..=200.0).text("radius"));
+        ui.add(egui::Slider::new(&mut self.falloff, 0.0..=4.0).text("falloff"));
+    }
+}
@@ -0,0 +1,78 @@
+use super::{vec3_ui, Attribute};
+
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Boids {
+    // where the flock flies to.
+    pub target: [f32; 3],
+    // how hard the particles steer to the target, 0 ignores it.
+    pub seek: f32,
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+    // how hard the particles turn away from the colliders.
+    pub avoidance: f32,
+    // the neighbors and the colliders farther than the radius are not seen.
+    pub view_radius: f32,
+    pub max_speed: f32,
+    pub min_speed: f32,
+    _pad: f32,
+}
+
+impl Boids {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        target: [f32; 3],
+        seek: f32,
+        separation: f32,
+        alignment: f32,
+        cohesion: f32,
+        avoidance: f32,
+        view_radius: f32,
+        max_speed: f32,
+        min_speed: f32,
+    ) -> Self {
+        Self {
+            target,
+            seek,
+            separation,
+            alignment,
+            cohesion,
+            avoidance,
+            view_radius,
+            max_speed,
+            min_speed,
+            _pad: 0.0,
+        }
+    }
+}
+
+impl Attribute for Boids {
+    fn name(&self) -> &'static str {
+        "boids"
+    }
+
+    fn source(&self) -> &'static str {
+        include_str!("../shaders/modules/boids.wgsl")
+    }
+
+    fn params(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.separation, 0.0..=50.0).text("separation"));
+        ui.add(egui::Slider::new(&mut self.alignment, 0.0..=10.0).text("alignment"));
+        ui.add(egui::Slider::new(&mut self.cohesion, 0.0..=10.0).text("cohesion"));
+        ui.add(egui::Slider::new(&mut self.avoidance, 0.0..=200.0).text("avoidance"));
+        ui.add(egui::Slider::new(&mut self.view_radius, 0.1..=20.0).text("view radius"));
+        ui.add(egui::Slider::new(&mut self.max_speed, 0.1..=100.0).text("max speed"));
+        ui.add(egui::Slider::new(&mut self.min_speed, 0.0..=self.max_speed).text("min speed"));
+        vec3_ui(ui, "target", &mut self.target);
+        ui.add(egui::Slider::new(&mut self.seek, 0.0..=10.0).text("seek"));
+    }
+
+    fn reach(&self) -> f32 {
+        self.view_radius
+    }
+}
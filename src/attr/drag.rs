@@ -0,0 +1,29 @@
+use super::Attribute;
+
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Drag {
+    // deceleration proportional to the speed.
+    pub linear: f32,
+    // deceleration proportional to the squared speed.
+    pub quadratic: f32,
+}
+
+impl Attribute for Drag {
+    fn name(&self) -> &'static str {
+        "drag"
+    }
+
+    fn source(&self) -> &'static str {
+        include_str!("../shaders/modules/drag.wgsl")
+    }
+
+    fn params(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.linear, 0.0..=10.0).text("linear"));
+        ui.add(egui::Slider::new(&mut self.quadratic, 0.0..=1.0).text("quadratic"));
+    }
+}
@@ -0,0 +1,35 @@
+use super::{vec3_ui, Attribute};
+
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Gravity {
+    pub acceleration: [f32; 3],
+    _pad: f32,
+}
+
+impl Gravity {
+    pub fn new(acceleration: [f32; 3]) -> Self {
+        Self {
+            acceleration,
+            _pad: 0.0,
+        }
+    }
+}
+
+impl Attribute for Gravity {
+    fn name(&self) -> &'static str {
+        "gravity"
+    }
+
+    fn source(&self) -> &'static str {
+        include_str!("../shaders/modules/gravity.wgsl")
+    }
+
+    fn params(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        vec3_ui(ui, "acceleration", &mut self.acceleration);
+    }
+}
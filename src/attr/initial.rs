@@ -0,0 +1,20 @@
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Initial {
+    // color of the spawned particles, they take the color of their origin when alpha is negative.
+    pub color: [f32; 4],
+    // range of every component of the direction given to spawned particles.
+    pub direction: [f32; 2],
+    // range of their velocity.
+    pub velocity: [f32; 2],
+}
+
+impl Default for Initial {
+    fn default() -> Self {
+        Self {
+            color: [0.0, 0.0, 0.0, -1.0],
+            direction: [0.0; 2],
+            velocity: [0.0; 2],
+        }
+    }
+}
@@ -0,0 +1,16 @@
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Lifetime {
+    // range of the lifetime given to spawned particles, a negative lifetime never expires.
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for Lifetime {
+    fn default() -> Self {
+        Self {
+            min: -1.0,
+            max: -1.0,
+        }
+    }
+}
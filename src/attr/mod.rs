@@ -1,59 +1,364 @@
+pub mod attractor;
+pub mod boids;
+pub mod drag;
+pub mod gravity;
+pub mod initial;
+pub mod lifetime;
+pub mod morph;
 pub mod position;
-pub mod velocity;
+pub mod scatter_reassemble;
+pub mod spawn;
+pub mod turbulence;
+pub mod vortex;
 
+pub use attractor::*;
+pub use boids::*;
+pub use drag::*;
+pub use gravity::*;
+pub use initial::*;
+pub use lifetime::*;
+pub use morph::*;
 pub use position::*;
-pub use velocity::*;
+pub use scatter_reassemble::*;
+pub use spawn::*;
+pub use turbulence::*;
+pub use vortex::*;
 
-use naga_oil::compose::{Composer, NagaModuleDescriptor};
+use std::collections::HashMap;
+
+use naga_oil::compose::{
+    ComposableModuleDescriptor, Composer, NagaModuleDescriptor, ShaderDefValue,
+};
 use wgpu::naga::Module;
+use wgpu::util::DeviceExt;
+
+use crate::particle::Particle;
+
+/// A composable piece of the particle update.
+///
+/// The [`source`](Attribute::source) is a naga_oil module imported as `vfx::<name>`, it must
+/// declare a `Params` struct and an `update` function:
+///
+/// ```wgsl
+/// #define_import_path vfx::<name>
+/// #import vfx::particle::{Particle, SimulationParams}
+///
+/// struct Params { ... }
+///
+/// fn update(particle: ptr<function, Particle>, params: Params, sim: SimulationParams) { ... }
+/// ```
+///
+/// `Params` is filled with the bytes of [`params`](Attribute::params) every frame, so both must
+/// share the same layout.
+pub trait Attribute {
+    fn name(&self) -> &'static str;
+
+    fn source(&self) -> &'static str;
+
+    fn params(&self) -> &[u8];
 
-use crate::uniform::Uniform;
+    /// egui controls editing the params.
+    fn ui(&mut self, ui: &mut egui::Ui);
 
+    /// How far the module looks for the neighbors of a particle in `vfx::grid`, the cells of the
+    /// grid can't be smaller.
+    fn reach(&self) -> f32 {
+        0.0
+    }
+}
+
+/// one drag value per component.
+pub(crate) fn vec3_ui(ui: &mut egui::Ui, label: &str, v: &mut [f32; 3]) {
+    ui.horizontal(|ui| {
+        for c in v.iter_mut() {
+            ui.add(egui::DragValue::new(c).speed(0.1));
+        }
+        ui.label(label);
+    });
+}
+
+/// modules every shader can import.
+const BUILTIN_MODULES: [(&str, &str); 5] = [
+    ("rand", include_str!("../shaders/modules/rand.wgsl")),
+    ("particle", include_str!("../shaders/modules/particle.wgsl")),
+    ("collider", include_str!("../shaders/modules/collider.wgsl")),
+    ("grid", include_str!("../shaders/modules/grid.wgsl")),
+    ("sph", include_str!("../shaders/modules/sph.wgsl")),
+];
+
+/// Composes the compute shader of an emitter: every [`Attribute`] is imported as a naga_oil
+/// module and called from `;;COMPUTE_CODE` in the order it was added.
 pub struct ShaderBuilder {
     main_code: String,
+    /// name and source of the imported attribute modules.
+    modules: Vec<(&'static str, &'static str)>,
+    /// name and size of the `Params` of every attribute, in `ModuleParams` order.
+    params: Vec<(&'static str, usize)>,
+    /// wgsl inserted in `;;COMPUTE_CODE`.
+    calls: Vec<String>,
+    /// shader defs of the `#ifdef`s of the main code.
+    defs: Vec<&'static str>,
 }
 
 impl ShaderBuilder {
+    pub fn new(main_code: &str) -> Self {
+        Self {
+            main_code: main_code.to_string(),
+            modules: vec![],
+            params: vec![],
+            calls: vec![],
+            defs: vec![],
+        }
+    }
+
+    pub fn attribute(&mut self, attribute: &dyn Attribute) -> &mut Self {
+        let name = attribute.name();
+        if !self.modules.iter().any(|(module, _)| *module == name) {
+            self.modules.push((name, attribute.source()));
+        }
+
+        let field = format!("m{}", self.params.len());
+        self.calls.push(format!(
+            "{name}::update(&particle, module_params.{field}, sim);"
+        ));
+        self.params.push((name, attribute.params().len()));
+        self
+    }
+
+    /// Inserts raw wgsl, it has access to `particle`, `particle_uniform` and `sim`, to
+    /// `particles_src` in ping-pong and to the `neighbors` of `vfx::grid` with a grid.
+    pub fn code(&mut self, code: &str) -> &mut Self {
+        self.calls.push(code.to_string());
+        self
+    }
+
+    /// Enables the `#ifdef name` blocks of the main code.
+    pub fn define(&mut self, name: &'static str) -> &mut Self {
+        self.defs.push(name);
+        self
+    }
+
+    /// The composed module, or the error of naga_oil as it would print it.
+    pub fn build(&self) -> Result<Module, String> {
+        let mut composer = composer();
+        for (name, source) in &self.modules {
+            add_module(&mut composer, name, source)?;
+        }
+
+        let mut header = String::new();
+        for (name, _) in &self.modules {
+            header += &format!("#import vfx::{name}\n");
+        }
+        if !self.params.is_empty() {
+            header += "struct ModuleParams {\n";
+            for (i, (name, _)) in self.params.iter().enumerate() {
+                header += &format!("  @align(16) m{i}: {name}::Params,\n");
+            }
+            header += "}\n@group(2) @binding(0) var<uniform> module_params: ModuleParams;\n";
+        }
+
+        let source = header
+            + &self
+                .main_code
+                .replace(";;COMPUTE_CODE", &self.calls.join("\n    "));
+        let defs = self
+            .defs
+            .iter()
+            .map(|name| (name.to_string(), ShaderDefValue::Bool(true)))
+            .collect();
+        let module = compose(&mut composer, &source, "./shaders/vfx_compute.wgsl", defs)?;
+        self.check_params(&module);
+        Ok(module)
+    }
+
+    /// Composes one of the shaders of the crate, they only import the [`BUILTIN_MODULES`] so an
+    /// error is a bug.
     pub fn build_module(source: &str) -> Module {
-        let mut composer = Composer::default();
-        let module = match composer.make_naga_module(NagaModuleDescriptor {
+        compose(
+            &mut composer(),
             source,
-            file_path: "./shaders/vfx_render.wgsl",
-            shader_defs: [(Default::default())].into(),
-            ..Default::default()
-        }) {
-            Ok(module) => Ok(module),
-            Err(e) => {
-                println!("error: {e:#?}");
-                Err(e)
+            "./shaders/vfx_render.wgsl",
+            Default::default(),
+        )
+        .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Panics if the `Params` of a module and the bytes of its [`Attribute`] disagree, the
+    /// uniform would be silently misread otherwise.
+    fn check_params(&self, module: &Module) {
+        let Some(members) = module.types.iter().find_map(|(_, ty)| match &ty.inner {
+            wgpu::naga::TypeInner::Struct { members, .. }
+                if ty.name.as_deref() == Some("ModuleParams") =>
+            {
+                Some(members)
             }
+            _ => None,
+        }) else {
+            return;
         };
-        let module = module.unwrap();
-        module
+
+        let (offsets, _) = params_layout(self.params.iter().map(|(_, size)| *size));
+        for ((member, (name, size)), offset) in members.iter().zip(&self.params).zip(offsets) {
+            let span = module.types[member.ty].inner.size(module.to_ctx()) as usize;
+            assert_eq!(
+                (member.offset as usize, span),
+                (offset, *size),
+                "`{name}::Params` (offset, size) does not match its attribute"
+            );
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
-#[repr(C)]
-pub struct AttrContext {
-    pub position: Position,
-    pub velocity: Velocity,
-    _pad: [f32; 3],
+fn add_module(composer: &mut Composer, name: &str, source: &str) -> Result<(), String> {
+    composer
+        .add_composable_module(ComposableModuleDescriptor {
+            source,
+            file_path: &format!("./shaders/modules/{name}.wgsl"),
+            ..Default::default()
+        })
+        .map(|_| ())
+        .map_err(|e| format!("invalid module vfx::{name}: {}", e.emit_to_string(composer)))
 }
 
-impl AttrContext {
-    pub fn update_uniform(&mut self, position: [f32; 4], velocity: f32) {
-        self.position.position = position;
-        self.velocity.vel += velocity;
+/// A composer with the [`BUILTIN_MODULES`] already added.
+fn composer() -> Composer {
+    let mut composer = Composer::default();
+    for (name, source) in BUILTIN_MODULES {
+        let source = source.replace(";;PARTICLE_STRUCT", &Particle::wgsl());
+        add_module(&mut composer, name, &source).unwrap_or_else(|e| panic!("{e}"));
     }
+    composer
+}
+
+fn compose(
+    composer: &mut Composer,
+    source: &str,
+    file_path: &str,
+    shader_defs: HashMap<String, ShaderDefValue>,
+) -> Result<Module, String> {
+    let module = composer
+        .make_naga_module(NagaModuleDescriptor {
+            source,
+            file_path,
+            shader_defs,
+            ..Default::default()
+        })
+        .map_err(|e| e.emit_to_string(composer))?;
+    Particle::check_layout(&module);
+    Ok(module)
 }
 
-impl Default for AttrContext {
-    fn default() -> Self {
+/// Offsets of every `Params` inside `ModuleParams` and the size of the whole struct, each member
+/// is aligned to 16 bytes as required by uniform buffers.
+fn params_layout(sizes: impl Iterator<Item = usize>) -> (Vec<usize>, usize) {
+    let mut offsets = vec![];
+    let mut end = 0usize;
+    for size in sizes {
+        let offset = end.next_multiple_of(16);
+        offsets.push(offset);
+        end = offset + size;
+    }
+    (offsets, end.next_multiple_of(16).max(16))
+}
+
+/// The attribute modules of an emitter and the `ModuleParams` uniform they share at
+/// **@group(2)**.
+pub struct Attributes {
+    pub modules: Vec<Box<dyn Attribute>>,
+    buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Attributes {
+    pub fn new(device: &wgpu::Device, modules: Vec<Box<dyn Attribute>>) -> Self {
+        let mut bytes = vec![];
+        pack_params(&modules, &mut bytes);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Attributes Uniform Buffer"),
+            contents: &bytes,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("Attributes Bind Group Layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("Attributes Bind Group"),
+        });
+
         Self {
-            position: Position::default(),
-            velocity: Velocity::default(),
-            _pad: [0.0, 0.0, 0.0],
+            modules,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue) {
+        let mut bytes = vec![];
+        pack_params(&self.modules, &mut bytes);
+        queue.write_buffer(&self.buffer, 0, &bytes);
+    }
+
+    /// The largest [`Attribute::reach`] of the modules.
+    pub fn reach(&self) -> f32 {
+        self.modules
+            .iter()
+            .map(|module| module.reach())
+            .fold(0.0, f32::max)
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        for (i, module) in self.modules.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
+                ui.label(module.name());
+                module.ui(ui);
+            });
         }
     }
 }
+
+/// Lays out the params of `modules` as the `ModuleParams` struct.
+fn pack_params(modules: &[Box<dyn Attribute>], bytes: &mut Vec<u8>) {
+    let (offsets, size) = params_layout(modules.iter().map(|module| module.params().len()));
+    bytes.resize(size, 0);
+    for (module, offset) in modules.iter().zip(offsets) {
+        let params = module.params();
+        bytes[offset..offset + params.len()].copy_from_slice(params);
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct AttrContext {
+    pub position: Position,
+    pub lifetime: Lifetime,
+    pub spawn: Spawn,
+    /// seed of the random streams of the emitter.
+    pub seed: u32,
+    /// the attributes `emit` gives to the recycled particles.
+    pub initial: Initial,
+}
+
+impl AttrContext {
+    pub fn update_uniform(&mut self, position: [f32; 4]) {
+        self.position.position = position;
+    }
+}
@@ -0,0 +1,78 @@
+use serde::Deserialize;
+
+use super::Attribute;
+
+/// Curve followed by the particles between their origin and their target.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Easing {
+    Linear,
+    #[default]
+    SmoothStep,
+    CubicInOut,
+    /// overshoots the target and settles back.
+    BackOut,
+}
+
+impl Easing {
+    const ALL: [Easing; 4] = [
+        Easing::Linear,
+        Easing::SmoothStep,
+        Easing::CubicInOut,
+        Easing::BackOut,
+    ];
+}
+
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Morph {
+    // 0 keeps the particles at their origin, 1 moves them to their target.
+    pub progress: f32,
+    // `Easing` as u32.
+    easing: u32,
+    // share of the morph the particles spend waiting for their own delay.
+    pub delay: f32,
+    // how far the particles stray from the straight path halfway through.
+    pub noise: f32,
+}
+
+impl Morph {
+    pub fn new(progress: f32, easing: Easing, delay: f32, noise: f32) -> Self {
+        Self {
+            progress,
+            easing: easing as u32,
+            delay,
+            noise,
+        }
+    }
+
+    pub fn easing(&self) -> Easing {
+        Easing::ALL[self.easing as usize]
+    }
+}
+
+impl Attribute for Morph {
+    fn name(&self) -> &'static str {
+        "morph"
+    }
+
+    fn source(&self) -> &'static str {
+        include_str!("../shaders/modules/morph.wgsl")
+    }
+
+    fn params(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.progress, 0.0..=1.0).text("progress"));
+        egui::ComboBox::from_label("easing")
+            .selected_text(format!("{:?}", self.easing()))
+            .show_ui(ui, |ui| {
+                for easing in Easing::ALL {
+                    ui.selectable_value(&mut self.easing, easing as u32, format!("{easing:?}"));
+                }
+            });
+        ui.add(egui::Slider::new(&mut self.delay, 0.0..=0.95).text("delay"));
+        ui.add(egui::Slider::new(&mut self.noise, 0.0..=50.0).text("noise"));
+    }
+}
@@ -0,0 +1,26 @@
+use super::Attribute;
+
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct ScatterReassemble {
+    // particles explode outward while it's positive and return to their origin while it's negative.
+    pub velocity: f32,
+}
+
+impl Attribute for ScatterReassemble {
+    fn name(&self) -> &'static str {
+        "scatter_reassemble"
+    }
+
+    fn source(&self) -> &'static str {
+        include_str!("../shaders/modules/scatter_reassemble.wgsl")
+    }
+
+    fn params(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.velocity, -100.0..=100.0).text("velocity"));
+    }
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Spawn {
+    // particles emitted in the current frame, computed on the cpu from the emission rate and bursts.
+    pub count: u32,
+}
@@ -0,0 +1,31 @@
+use super::Attribute;
+
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Turbulence {
+    pub strength: f32,
+    // scale of the curl noise, higher values give smaller swirls.
+    pub frequency: f32,
+    // how fast the noise field scrolls over time.
+    pub speed: f32,
+}
+
+impl Attribute for Turbulence {
+    fn name(&self) -> &'static str {
+        "turbulence"
+    }
+
+    fn source(&self) -> &'static str {
+        include_str!("../shaders/modules/turbulence.wgsl")
+    }
+
+    fn params(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.strength, 0.0..=100.0).text("strength"));
+        ui.add(egui::Slider::new(&mut self.frequency, 0.01..=2.0).text("frequency"));
+        ui.add(egui::Slider::new(&mut self.speed, 0.0..=5.0).text("speed"));
+    }
+}
@@ -0,0 +1,36 @@
+use super::{vec3_ui, Attribute};
+
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Vortex {
+    pub center: [f32; 3],
+    // tangential acceleration next to the axis, negative values spin the other way.
+    pub strength: f32,
+    pub axis: [f32; 3],
+    // distance to the axis where the vortex fades out.
+    pub radius: f32,
+}
+
+impl Attribute for Vortex {
+    fn name(&self) -> &'static str {
+        "vortex"
+    }
+
+    fn source(&self) -> &'static str {
+        include_str!("../shaders/modules/vortex.wgsl")
+    }
+
+    fn params(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        vec3_ui(ui, "center", &mut self.center);
+        vec3_ui(ui, "axis", &mut self.axis);
+        if self.axis == [0.0; 3] {
+            self.axis = [0.0, 1.0, 0.0];
+        }
+        ui.add(egui::Slider::new(&mut self.strength, -100.0..=100.0).text("strength"));
+        ui.add(egui::Slider::new(&mut self.radius, 0.1..=200.0).text("radius"));
+    }
+}
@@ -0,0 +1,46 @@
+//! Renders the particle system without a window and writes every frame as a PNG.
+//!
+//! `headless [--frames N] [--size WIDTHxHEIGHT] [--dt SECONDS] [--out DIR] [--scene FILE] [--seed N] [--fallback]`
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> anyhow::Result<()> {
+    use anyhow::Context;
+    use particle_system::{run_headless, HeadlessOptions, SceneDesc};
+
+    let mut options = HeadlessOptions::default();
+    let mut args = std::env::args().skip(1);
+    let mut seed = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--frames" => options.frames = value()?.parse()?,
+            "--dt" => options.dt = instant::Duration::from_secs_f64(value()?.parse()?),
+            "--out" => options.output = value()?.into(),
+            "--size" => {
+                let size = value()?;
+                let (width, height) = size
+                    .split_once('x')
+                    .with_context(|| format!("Invalid size {size}, expected WIDTHxHEIGHT"))?;
+                options.size = (width.parse()?, height.parse()?);
+            }
+            "--scene" => options.scene = SceneDesc::from_file(value()?)?,
+            "--seed" => seed = Some(value()?.parse()?),
+            "--fallback" => options.force_fallback_adapter = true,
+            _ => anyhow::bail!("Unknown argument {arg}"),
+        }
+    }
+
+    // applied after the loop so it also overrides the seed of a `--scene` given later.
+    if let Some(seed) = seed {
+        options.scene.seed = seed;
+    }
+
+    run_headless(options)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,114 @@
+//! The colliders of an emitter as the `simulate` kernel reads them, see the `vfx::collider` wgsl
+//! module.
+
+use cgmath::{Deg, Euler, InnerSpace, Quaternion, Vector3};
+
+use crate::attr::vec3_ui;
+use crate::emitter::{Collider, ColliderShape};
+
+/// Colliders an emitter can have, the length of the `colliders` array in wgsl.
+pub const MAX_COLLIDERS: usize = 16;
+
+const PLANE: u32 = 0;
+const SPHERE: u32 = 1;
+const BOX: u32 = 2;
+const DEPTH: u32 = 3;
+
+/// A [`Collider`] laid out like the wgsl `Collider`.
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct ColliderParams {
+    /// a point of the plane, or the center of the sphere and of the box.
+    pub center: [f32; 3],
+    kind: u32,
+    /// the unit normal of the plane, the size of the box, or the radius of the sphere and the
+    /// thickness of the depth in `x`.
+    pub size: [f32; 3],
+    pub restitution: f32,
+    /// quaternion of the box, `xyz` then `w`.
+    rotation: [f32; 4],
+    pub friction: f32,
+    kill: u32,
+    inside: u32,
+    _pad: u32,
+}
+
+impl ColliderParams {
+    pub fn new(collider: &Collider) -> Self {
+        let (kind, center, size, rotation) = match collider.shape {
+            ColliderShape::Plane { point, normal } => {
+                let normal = Vector3::from(normal).normalize();
+                (PLANE, point, normal.into(), [0.0, 0.0, 0.0, 1.0])
+            }
+            ColliderShape::Sphere { center, radius } => {
+                (SPHERE, center, [radius, 0.0, 0.0], [0.0, 0.0, 0.0, 1.0])
+            }
+            ColliderShape::Box {
+                center,
+                size,
+                rotation: [x, y, z],
+            } => {
+                let q = Quaternion::from(Euler::new(Deg(x), Deg(y), Deg(z)));
+                (BOX, center, size, [q.v.x, q.v.y, q.v.z, q.s])
+            }
+            ColliderShape::Depth { thickness } => {
+                (DEPTH, [0.0; 3], [thickness, 0.0, 0.0], [0.0, 0.0, 0.0, 1.0])
+            }
+        };
+        Self {
+            center,
+            kind,
+            size,
+            restitution: collider.restitution,
+            rotation,
+            friction: collider.friction,
+            kill: collider.kill as u32,
+            inside: collider.inside as u32,
+            _pad: 0,
+        }
+    }
+}
+
+/// The `Colliders` uniform of the compute shader, only the first `count` colliders are tested.
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct ColliderUniform {
+    pub colliders: [ColliderParams; MAX_COLLIDERS],
+    pub count: u32,
+    _pad: [u32; 3],
+}
+
+impl ColliderUniform {
+    pub fn new(colliders: &[Collider]) -> Self {
+        assert!(colliders.len() <= MAX_COLLIDERS, "too many colliders");
+        let mut uniform = Self {
+            count: colliders.len() as u32,
+            ..Default::default()
+        };
+        for (params, collider) in uniform.colliders.iter_mut().zip(colliders) {
+            *params = ColliderParams::new(collider);
+        }
+        uniform
+    }
+
+    /// egui controls moving the colliders and editing how the particles bounce.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let count = self.count as usize;
+        for (i, collider) in self.colliders[..count].iter_mut().enumerate() {
+            let kind = match collider.kind {
+                PLANE => "plane",
+                SPHERE => "sphere",
+                BOX => "box",
+                _ => "depth",
+            };
+            ui.label(format!("collider {i}: {kind}"));
+            if collider.kind == DEPTH {
+                ui.add(egui::Slider::new(&mut collider.size[0], 0.0..=10.0).text("thickness"));
+            } else {
+                vec3_ui(ui, "center", &mut collider.center);
+            }
+            ui.add(egui::Slider::new(&mut collider.restitution, 0.0..=1.0).text("restitution"));
+            ui.add(egui::Slider::new(&mut collider.friction, 0.0..=1.0).text("friction"));
+        }
+    }
+}
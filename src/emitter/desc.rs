@@ -0,0 +1,1357 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::grid::MAX_GRID_CELLS;
+use super::source::Sampling;
+
+use crate::attr::{
+    Attractor, Attribute, Boids, Drag, Easing, Gravity, Morph, ScatterReassemble, Turbulence,
+    Vortex,
+};
+use crate::collider::MAX_COLLIDERS;
+use crate::particle::Particle;
+use crate::timestep::MAX_SUBSTEPS;
+
+/// The most particles a storage binding can address, devices usually allow fewer, see
+/// [`max_capacity`](super::max_capacity).
+pub const MAX_CAPACITY: u32 = u32::MAX / size_of::<Particle>() as u32;
+
+/// Declarative description of a scene, usually loaded from a `.ron` file:
+///
+/// ```ron
+/// SceneDesc(
+///     emitters: [
+///         (
+///             name: "sphere",
+///             capacity: 250000,
+///             spawn: Sphere(radius: 20.0),
+///             update: [ScatterReassemble(velocity: -10.0)],
+///             render: (particle_size: 0.2, blend: Alpha),
+///         ),
+///         (name: "image", spawn: Image(path: "picture.png")),
+///     ],
+///     bloom: (enabled: true),
+/// )
+/// ```
+///
+/// Every field is optional and falls back to the values of [`SceneDesc::default`] and
+/// [`EmitterDesc::default`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneDesc {
+    /// seed of every random number, the same seed always gives the same simulation.
+    pub seed: u32,
+    pub emitters: Vec<EmitterDesc>,
+    pub bloom: BloomSettings,
+    /// simulates in steps of a fixed length instead of the frame time.
+    pub timestep: Option<TimestepSettings>,
+    pub simulation: SimulationSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmitterDesc {
+    /// shown in the DEBUG window.
+    pub name: String,
+    /// number of particles simulated by the emitter, an upper bound for `SpawnShape::Image`.
+    pub capacity: u32,
+    pub spawn: SpawnShape,
+    /// second shape the `Morph` update module takes the particles to, it has as many points as
+    /// `spawn`. Without it the target of every particle is its origin.
+    pub target: Option<SpawnShape>,
+    /// when set the emitter starts empty and spawns particles over time, otherwise every
+    /// particle is alive from the start.
+    pub emission: Option<Emission>,
+    pub initial: InitialAttributes,
+    /// compute snippets executed in order by the `simulate` entry point.
+    pub update: Vec<UpdateModule>,
+    /// shapes the particles bounce on after they moved, at most [`MAX_COLLIDERS`].
+    pub colliders: Vec<Collider>,
+    /// simulates from a read-only copy of the particles into a second buffer, so the update
+    /// modules can read the other particles from `particles_src` while they move. It doubles the
+    /// memory of the particles and new particles are simulated from the step after they spawn.
+    pub ping_pong: bool,
+    /// sorts the particles in a spatial hash grid before every step, the update modules walk the
+    /// neighbors of a particle with the `vfx::grid` wgsl module. It needs `ping_pong`.
+    pub grid: Option<GridSettings>,
+    /// simulates the particles as a liquid with smoothed-particle hydrodynamics, on top of the
+    /// gravity and the update modules. It needs a `grid`, its `cell_size` is the smoothing
+    /// radius, and the colliders are the walls of the container.
+    pub fluid: Option<FluidSettings>,
+    pub render: RenderSettings,
+}
+
+/// Where the particles are placed (their `origin`) when the emitter is created.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum SpawnShape {
+    /// one particle per sampled pixel, mapped to a plane of `scale` units. When `path` is not set
+    /// the embedded `hemis.jpg` is used.
+    ///
+    /// The `capacity` of the emitter is the particle budget: larger images are reduced to fit it
+    /// and the emitter gets one particle per kept pixel.
+    Image {
+        #[serde(default)]
+        path: Option<PathBuf>,
+        #[serde(default = "default_image_scale")]
+        scale: f32,
+        #[serde(default)]
+        sampling: Sampling,
+        /// pixels with a lower alpha are dropped.
+        #[serde(default)]
+        alpha_threshold: f32,
+        /// pushes the bright pixels towards +z by up to `depth` units.
+        #[serde(default)]
+        depth: f32,
+        /// samples the image in a compute pass instead of on the cpu, the emitter keeps one
+        /// particle per cell of the reduced image and the ones below `alpha_threshold` are never
+        /// alive.
+        #[serde(default)]
+        gpu: bool,
+    },
+    /// `text` rasterized with a `.ttf` or `.otf` font (the default egui font when `font` is not
+    /// set) and sampled like an image: one particle per covered pixel within the budget. Each
+    /// line is `pixel_height` pixels high before the image is reduced.
+    Text {
+        text: String,
+        #[serde(default)]
+        font: Option<PathBuf>,
+        #[serde(default = "default_pixel_height")]
+        pixel_height: f32,
+        #[serde(default = "default_image_scale")]
+        scale: f32,
+        #[serde(default = "default_text_color")]
+        color: [f32; 4],
+        #[serde(default)]
+        sampling: Sampling,
+        /// pixels less covered are dropped.
+        #[serde(default = "default_text_alpha_threshold")]
+        alpha_threshold: f32,
+    },
+    /// the points of a `.ply` or `.xyz` file and their colors, the capacity is an upper bound: larger
+    /// clouds keep evenly spaced points.
+    PointCloud {
+        path: PathBuf,
+        #[serde(default = "default_scale")]
+        scale: f32,
+    },
+    /// `capacity` points spread uniformly over the surface of an `.obj` mesh, colored by its
+    /// vertex colors, by `texture` (or the diffuse texture of its material) or by the diffuse
+    /// color of its material.
+    Mesh {
+        path: PathBuf,
+        #[serde(default = "default_scale")]
+        scale: f32,
+        #[serde(default)]
+        texture: Option<PathBuf>,
+    },
+    /// random points on the surface of a sphere.
+    Sphere {
+        radius: f32,
+    },
+    /// random points inside a cube.
+    Cube {
+        size: f32,
+    },
+    Point,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Emission {
+    /// particles per second.
+    pub rate: f32,
+    pub bursts: Vec<Burst>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Burst {
+    /// seconds since the emitter was created.
+    pub time: f32,
+    pub count: u32,
+    /// repeats the burst every `interval` seconds.
+    #[serde(default)]
+    pub interval: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InitialAttributes {
+    /// overrides the color given by the spawn shape.
+    pub color: Option<[f32; 4]>,
+    /// range of every component of the initial direction.
+    pub direction: (f32, f32),
+    /// range of the initial velocity.
+    pub velocity: (f32, f32),
+    /// range of the lifetime in seconds, particles live forever when it's not set.
+    pub lifetime: Option<(f32, f32)>,
+}
+
+/// A solid the particles can't enter, or a container they can't leave when `inside` is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    /// fraction of the normal velocity kept by the bounce, 0 stops it and 1 is a perfect bounce.
+    #[serde(default = "default_restitution")]
+    pub restitution: f32,
+    /// fraction of the tangent velocity lost on contact.
+    #[serde(default)]
+    pub friction: f32,
+    /// the particles touching the collider die instead of bouncing.
+    #[serde(default)]
+    pub kill: bool,
+    /// keeps the particles inside the sphere or the box, it has no effect on a plane or the depth.
+    #[serde(default)]
+    pub inside: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum ColliderShape {
+    /// the half-space behind the plane going through `point`.
+    Plane {
+        point: [f32; 3],
+        normal: [f32; 3],
+    },
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+    },
+    /// a box of `size` units, `rotation` is in degrees around x, then y, then z. It's axis
+    /// aligned without rotation.
+    Box {
+        center: [f32; 3],
+        size: [f32; 3],
+        #[serde(default)]
+        rotation: [f32; 3],
+    },
+    /// the occluders drawn in the depth prepass, as seen from the camera. The particles up to
+    /// `thickness` units behind the visible surface are inside it.
+    Depth {
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum UpdateModule {
+    /// particles explode outward while `velocity` is positive and return to their origin while
+    /// it's negative.
+    ScatterReassemble { velocity: f32 },
+    /// constant acceleration.
+    Gravity { acceleration: [f32; 3] },
+    /// slows the particles down by `linear * speed + quadratic * speed²`, the speed is relative
+    /// to the `wind` of the simulation.
+    Drag {
+        #[serde(default)]
+        linear: f32,
+        #[serde(default)]
+        quadratic: f32,
+    },
+    /// pulls the particles closer than `radius` towards `position`, a negative `strength`
+    /// pushes them away. The pull fades as `(1 - distance / radius) ^ falloff`.
+    Attractor {
+        position: [f32; 3],
+        strength: f32,
+        radius: f32,
+        #[serde(default = "default_falloff")]
+        falloff: f32,
+    },
+    /// spins the particles closer than `radius` around the axis going through `center`.
+    Vortex {
+        #[serde(default)]
+        center: [f32; 3],
+        #[serde(default = "default_vortex_axis")]
+        axis: [f32; 3],
+        strength: f32,
+        radius: f32,
+    },
+    /// curl-noise turbulence scrolling at `speed`.
+    Turbulence {
+        strength: f32,
+        frequency: f32,
+        #[serde(default)]
+        speed: f32,
+    },
+    /// moves the particles from their origin to the `target` shape of the emitter as `progress`
+    /// goes from 0 to 1, blending their colors. Each particle starts after its own share of
+    /// `delay` and strays by up to `noise` units from the straight path. It sets the velocity, so
+    /// it must come after the forces.
+    Morph {
+        #[serde(default)]
+        progress: f32,
+        #[serde(default)]
+        easing: Easing,
+        #[serde(default)]
+        delay: f32,
+        #[serde(default)]
+        noise: f32,
+    },
+    /// flocking: the particles steer away from the neighbors closer than `view_radius`, match
+    /// their velocity and fly to their center, scaled by `separation`, `alignment` and
+    /// `cohesion`. They turn away from the colliders closer than `view_radius` and towards
+    /// `target` by `avoidance` and `seek`, their speed stays between `min_speed` and `max_speed`.
+    /// It needs a `grid`, `view_radius` is at most its `cell_size`.
+    Boids {
+        separation: f32,
+        alignment: f32,
+        cohesion: f32,
+        view_radius: f32,
+        max_speed: f32,
+        #[serde(default)]
+        min_speed: f32,
+        #[serde(default)]
+        avoidance: f32,
+        #[serde(default)]
+        target: [f32; 3],
+        #[serde(default)]
+        seek: f32,
+    },
+    /// raw wgsl inserted in the `simulate` entry point, it has access to `particle`,
+    /// `particle_uniform` and the `SimulationParams` of the step as `sim`.
+    Wgsl(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    pub particle_size: f32,
+    pub blend: BlendMode,
+    /// the particles are drawn in the depth prepass, the emitters with a `Depth` collider bounce
+    /// on them.
+    pub occluder: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum BlendMode {
+    Additive,
+    Alpha,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    /// colors below this value are not bloomed.
+    pub threshold: f32,
+    pub intensity: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimestepSettings {
+    /// length of a step in seconds.
+    pub step: f32,
+    /// steps simulated at most in a frame.
+    pub max_substeps: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GridSettings {
+    /// size of the cells, the neighbors of a particle are in its cell or the 26 around it.
+    pub cell_size: f32,
+    /// entries of the hash table shared by the cells, at most [`MAX_GRID_CELLS`]. The capacity of
+    /// the emitter up to that by default.
+    #[serde(default)]
+    pub cells: Option<u32>,
+}
+
+/// The fluid of an emitter, the `stiffness`, `viscosity` and `surface_tension` can be edited in
+/// the DEBUG window.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FluidSettings {
+    /// density of the fluid at rest, the particles are pushed apart above it.
+    pub rest_density: f32,
+    /// pressure per unit of density above `rest_density`.
+    pub stiffness: f32,
+    pub viscosity: f32,
+    /// pulls the particles towards their neighbors, it keeps the surface together.
+    pub surface_tension: f32,
+    /// mass of every particle.
+    pub mass: f32,
+}
+
+/// Forces and limits shared by every emitter, they can be edited in the DEBUG window.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationSettings {
+    /// acceleration of every particle, on top of the `Gravity` modules.
+    pub gravity: [f32; 3],
+    /// velocity of the air, the `Drag` modules slow the particles relative to it.
+    pub wind: [f32; 3],
+    /// the particles leaving this box die.
+    pub bounds: Option<Bounds>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+fn default_restitution() -> f32 {
+    0.5
+}
+
+fn default_thickness() -> f32 {
+    1.0
+}
+
+fn default_pixel_height() -> f32 {
+    128.0
+}
+
+fn default_text_color() -> [f32; 4] {
+    [1.0; 4]
+}
+
+fn default_text_alpha_threshold() -> f32 {
+    0.5
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_falloff() -> f32 {
+    1.0
+}
+
+fn default_vortex_axis() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_image_scale() -> f32 {
+    30.0
+}
+
+impl Default for SceneDesc {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            emitters: vec![EmitterDesc::default()],
+            bloom: BloomSettings::default(),
+            timestep: None,
+            simulation: SimulationSettings::default(),
+        }
+    }
+}
+
+impl Default for EmitterDesc {
+    fn default() -> Self {
+        Self {
+            name: "emitter".to_string(),
+            capacity: 1000 * 937,
+            spawn: SpawnShape::Image {
+                path: None,
+                scale: default_image_scale(),
+                sampling: Sampling::default(),
+                alpha_threshold: 0.0,
+                depth: 0.0,
+                gpu: false,
+            },
+            target: None,
+            emission: None,
+            initial: InitialAttributes::default(),
+            update: vec![UpdateModule::ScatterReassemble { velocity: 0.0 }],
+            colliders: vec![],
+            ping_pong: false,
+            grid: None,
+            fluid: None,
+            render: RenderSettings::default(),
+        }
+    }
+}
+
+impl Default for FluidSettings {
+    fn default() -> Self {
+        Self {
+            rest_density: 1.0,
+            stiffness: 200.0,
+            viscosity: 0.5,
+            surface_tension: 0.0,
+            mass: 1.0,
+        }
+    }
+}
+
+impl Default for InitialAttributes {
+    fn default() -> Self {
+        Self {
+            color: None,
+            direction: (0.0, 0.1),
+            velocity: (-0.1, 0.1),
+            lifetime: None,
+        }
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            particle_size: 0.1,
+            blend: BlendMode::Additive,
+            occluder: false,
+        }
+    }
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 0.7,
+            intensity: 5.0,
+        }
+    }
+}
+
+impl Default for TimestepSettings {
+    fn default() -> Self {
+        Self {
+            step: 1.0 / 60.0,
+            max_substeps: 4,
+        }
+    }
+}
+
+impl SceneDesc {
+    /// The description shipped with the crate, it's the one used when no file is given.
+    pub fn builtin() -> Self {
+        Self::from_ron(include_str!("../scenes/hemis.ron"))
+            .expect("The builtin scene description is invalid")
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, DescError> {
+        let desc = Self::parse(source)?;
+        desc.validate()?;
+        Ok(desc)
+    }
+
+    /// Loads a description from disk, relative paths inside it are resolved from the directory
+    /// of the file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, DescError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|e| DescError::Io(path.into(), e))?;
+        let mut desc = Self::parse(&source)?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        for emitter in &mut desc.emitters {
+            emitter.resolve_paths(dir);
+        }
+
+        desc.validate()?;
+        Ok(desc)
+    }
+
+    fn parse(source: &str) -> Result<Self, DescError> {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .map_err(DescError::Parse)
+    }
+
+    /// Checks every field and reports all the invalid ones at once.
+    pub fn validate(&self) -> Result<(), DescError> {
+        let mut errors = vec![];
+
+        if self.emitters.is_empty() {
+            errors.push(FieldError {
+                field: "emitters".to_string(),
+                message: "at least one emitter is required".to_string(),
+            });
+        }
+        for (i, emitter) in self.emitters.iter().enumerate() {
+            emitter.check(&format!("emitters[{i}]."), &mut errors);
+        }
+
+        let mut check = Checker::new("bloom.", &mut errors);
+        check.field(
+            self.bloom.threshold >= 0.0,
+            "threshold",
+            format!("must not be negative, got {}", self.bloom.threshold),
+        );
+        check.field(
+            self.bloom.intensity >= 0.0,
+            "intensity",
+            format!("must not be negative, got {}", self.bloom.intensity),
+        );
+
+        if let Some(timestep) = &self.timestep {
+            let mut check = Checker::new("timestep.", &mut errors);
+            check.field(
+                timestep.step > 0.0,
+                "step",
+                format!("must be positive, got {}", timestep.step),
+            );
+            check.field(
+                (1..=MAX_SUBSTEPS).contains(&timestep.max_substeps),
+                "max_substeps",
+                format!(
+                    "must be between 1 and {MAX_SUBSTEPS}, got {}",
+                    timestep.max_substeps
+                ),
+            );
+        }
+
+        if let Some(Bounds { min, max }) = self.simulation.bounds {
+            let mut check = Checker::new("simulation.", &mut errors);
+            check.field(
+                min.iter().zip(max).all(|(min, max)| *min < max),
+                "bounds",
+                format!("min must be below max, got {min:?} and {max:?}"),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(DescError::Invalid(errors))
+        }
+    }
+}
+
+impl EmitterDesc {
+    /// The emitter has a `Depth` collider, its compute shader reads the depth prepass.
+    pub fn depth_collision(&self) -> bool {
+        self.colliders
+            .iter()
+            .any(|collider| matches!(collider.shape, ColliderShape::Depth { .. }))
+    }
+
+    fn resolve_paths(&mut self, dir: &Path) {
+        self.spawn.resolve_paths(dir);
+        if let Some(target) = &mut self.target {
+            target.resolve_paths(dir);
+        }
+    }
+
+    /// Appends the invalid fields to `errors`, prefixed by `prefix`.
+    fn check(&self, prefix: &str, errors: &mut Vec<FieldError>) {
+        let mut check = Checker::new(prefix, errors);
+
+        check.field(
+            self.capacity > 0 && self.capacity <= MAX_CAPACITY,
+            "capacity",
+            format!(
+                "must be between 1 and {MAX_CAPACITY}, got {}",
+                self.capacity
+            ),
+        );
+
+        self.spawn.check(&mut check, "spawn");
+        if let Some(target) = &self.target {
+            target.check(&mut check, "target");
+        }
+
+        let (min, max) = self.initial.direction;
+        check.field(
+            min <= max,
+            "initial.direction",
+            format!("empty range ({min}, {max})"),
+        );
+        let (min, max) = self.initial.velocity;
+        check.field(
+            min <= max,
+            "initial.velocity",
+            format!("empty range ({min}, {max})"),
+        );
+
+        if let Some((min, max)) = self.initial.lifetime {
+            check.field(
+                min >= 0.0 && min <= max,
+                "initial.lifetime",
+                format!("expected a positive range, got ({min}, {max})"),
+            );
+        }
+
+        if let Some(emission) = &self.emission {
+            check.field(
+                emission.rate >= 0.0,
+                "emission.rate",
+                format!("must not be negative, got {}", emission.rate),
+            );
+            for (i, burst) in emission.bursts.iter().enumerate() {
+                check.field(
+                    burst.time >= 0.0,
+                    &format!("emission.bursts[{i}].time"),
+                    format!("must not be negative, got {}", burst.time),
+                );
+                if let Some(interval) = burst.interval {
+                    check.field(
+                        interval > 0.0,
+                        &format!("emission.bursts[{i}].interval"),
+                        format!("must be positive, got {interval}"),
+                    );
+                }
+            }
+        }
+
+        for (i, module) in self.update.iter().enumerate() {
+            let field = |name: &str| format!("update[{i}].{name}");
+            match module {
+                UpdateModule::Drag { linear, quadratic } => {
+                    check.field(
+                        *linear >= 0.0,
+                        &field("linear"),
+                        format!("must not be negative, got {linear}"),
+                    );
+                    check.field(
+                        *quadratic >= 0.0,
+                        &field("quadratic"),
+                        format!("must not be negative, got {quadratic}"),
+                    );
+                }
+                UpdateModule::Attractor {
+                    radius, falloff, ..
+                } => {
+                    check.field(
+                        *radius > 0.0,
+                        &field("radius"),
+                        format!("must be positive, got {radius}"),
+                    );
+                    check.field(
+                        *falloff >= 0.0,
+                        &field("falloff"),
+                        format!("must not be negative, got {falloff}"),
+                    );
+                }
+                UpdateModule::Vortex { axis, radius, .. } => {
+                    check.field(
+                        *axis != [0.0; 3],
+                        &field("axis"),
+                        "must not be zero".to_string(),
+                    );
+                    check.field(
+                        *radius > 0.0,
+                        &field("radius"),
+                        format!("must be positive, got {radius}"),
+                    );
+                }
+                UpdateModule::Turbulence { frequency, .. } => check.field(
+                    *frequency > 0.0,
+                    &field("frequency"),
+                    format!("must be positive, got {frequency}"),
+                ),
+                UpdateModule::Morph { delay, noise, .. } => {
+                    check.field(
+                        self.target.is_some(),
+                        &format!("update[{i}]"),
+                        "`Morph` needs a `target` shape".to_string(),
+                    );
+                    check.field(
+                        (0.0..1.0).contains(delay),
+                        &field("delay"),
+                        format!("must be between 0 and 1 (excluded), got {delay}"),
+                    );
+                    check.field(
+                        *noise >= 0.0,
+                        &field("noise"),
+                        format!("must not be negative, got {noise}"),
+                    );
+                }
+                UpdateModule::Boids {
+                    separation,
+                    alignment,
+                    cohesion,
+                    view_radius,
+                    max_speed,
+                    min_speed,
+                    avoidance,
+                    seek,
+                    ..
+                } => {
+                    match &self.grid {
+                        Some(grid) => check.field(
+                            *view_radius > 0.0 && *view_radius <= grid.cell_size,
+                            &field("view_radius"),
+                            format!(
+                                "must be positive and at most the grid cell size {}, got \
+                                 {view_radius}",
+                                grid.cell_size
+                            ),
+                        ),
+                        None => check.field(
+                            false,
+                            &format!("update[{i}]"),
+                            "`Boids` needs a `grid`, the neighbors are found in it".to_string(),
+                        ),
+                    }
+                    check.field(
+                        *max_speed > 0.0,
+                        &field("max_speed"),
+                        format!("must be positive, got {max_speed}"),
+                    );
+                    check.field(
+                        *min_speed >= 0.0 && min_speed <= max_speed,
+                        &field("min_speed"),
+                        format!("must be between 0 and `max_speed`, got {min_speed}"),
+                    );
+                    for (name, value) in [
+                        ("separation", separation),
+                        ("alignment", alignment),
+                        ("cohesion", cohesion),
+                        ("avoidance", avoidance),
+                        ("seek", seek),
+                    ] {
+                        check.field(
+                            *value >= 0.0,
+                            &field(name),
+                            format!("must not be negative, got {value}"),
+                        );
+                    }
+                }
+                UpdateModule::Wgsl(code) => check.field(
+                    !code.trim().is_empty(),
+                    &format!("update[{i}]"),
+                    "empty wgsl code".to_string(),
+                ),
+                UpdateModule::ScatterReassemble { .. } | UpdateModule::Gravity { .. } => {}
+            }
+        }
+
+        check.field(
+            self.colliders.len() <= MAX_COLLIDERS,
+            "colliders",
+            format!(
+                "at most {MAX_COLLIDERS} colliders, got {}",
+                self.colliders.len()
+            ),
+        );
+        for (i, collider) in self.colliders.iter().enumerate() {
+            collider.check(&mut check, &format!("colliders[{i}]"));
+        }
+        if let Some(grid) = &self.grid {
+            check.field(
+                self.ping_pong,
+                "grid",
+                "needs `ping_pong`, the neighbors are read from `particles_src`".to_string(),
+            );
+            check.field(
+                grid.cell_size > 0.0,
+                "grid.cell_size",
+                format!("must be positive, got {}", grid.cell_size),
+            );
+            if let Some(cells) = grid.cells {
+                check.field(
+                    (1..=MAX_GRID_CELLS).contains(&cells),
+                    "grid.cells",
+                    format!("must be between 1 and {MAX_GRID_CELLS}, got {cells}"),
+                );
+            }
+        }
+
+        if let Some(fluid) = &self.fluid {
+            check.field(
+                self.grid.is_some(),
+                "fluid",
+                "needs a `grid`, its `cell_size` is the smoothing radius".to_string(),
+            );
+            check.field(
+                fluid.rest_density > 0.0,
+                "fluid.rest_density",
+                format!("must be positive, got {}", fluid.rest_density),
+            );
+            check.field(
+                fluid.mass > 0.0,
+                "fluid.mass",
+                format!("must be positive, got {}", fluid.mass),
+            );
+            for (name, value) in [
+                ("stiffness", fluid.stiffness),
+                ("viscosity", fluid.viscosity),
+                ("surface_tension", fluid.surface_tension),
+            ] {
+                check.field(
+                    value >= 0.0,
+                    &format!("fluid.{name}"),
+                    format!("must not be negative, got {value}"),
+                );
+            }
+        }
+
+        check.field(
+            !(self.render.occluder && self.depth_collision()),
+            "render.occluder",
+            "an occluder can't collide with the depth it draws".to_string(),
+        );
+
+        check.field(
+            self.render.particle_size > 0.0,
+            "render.particle_size",
+            format!("must be positive, got {}", self.render.particle_size),
+        );
+    }
+}
+
+impl Collider {
+    fn check(&self, check: &mut Checker, name: &str) {
+        let field = |field: &str| format!("{name}.{field}");
+        check.field(
+            (0.0..=1.0).contains(&self.restitution),
+            &field("restitution"),
+            format!("must be between 0 and 1, got {}", self.restitution),
+        );
+        check.field(
+            (0.0..=1.0).contains(&self.friction),
+            &field("friction"),
+            format!("must be between 0 and 1, got {}", self.friction),
+        );
+        match self.shape {
+            ColliderShape::Plane { normal, .. } => check.field(
+                normal != [0.0; 3],
+                &field("shape.normal"),
+                "must not be zero".to_string(),
+            ),
+            ColliderShape::Sphere { radius, .. } => check.field(
+                radius > 0.0,
+                &field("shape.radius"),
+                format!("must be positive, got {radius}"),
+            ),
+            ColliderShape::Box { size, .. } => check.field(
+                size.iter().all(|c| *c > 0.0),
+                &field("shape.size"),
+                format!("must be positive, got {size:?}"),
+            ),
+            ColliderShape::Depth { thickness } => check.field(
+                thickness > 0.0,
+                &field("shape.thickness"),
+                format!("must be positive, got {thickness}"),
+            ),
+        }
+    }
+}
+
+impl SpawnShape {
+    fn resolve_paths(&mut self, dir: &Path) {
+        let paths = match self {
+            Self::Image { path, .. } => vec![path.as_mut()],
+            Self::Text { font, .. } => vec![font.as_mut()],
+            Self::PointCloud { path, .. } => vec![Some(path)],
+            Self::Mesh { path, texture, .. } => vec![Some(path), texture.as_mut()],
+            _ => vec![],
+        };
+        for path in paths.into_iter().flatten() {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        }
+    }
+
+    /// Appends the invalid fields of the shape, `name` is the field holding it.
+    fn check(&self, check: &mut Checker, name: &str) {
+        let field = |field: &str| format!("{name}.{field}");
+        match self {
+            Self::Image {
+                path,
+                scale,
+                alpha_threshold,
+                ..
+            } => {
+                check.field(
+                    *scale > 0.0,
+                    &field("scale"),
+                    format!("must be positive, got {scale}"),
+                );
+                check.field(
+                    (0.0..=1.0).contains(alpha_threshold),
+                    &field("alpha_threshold"),
+                    format!("must be between 0 and 1, got {alpha_threshold}"),
+                );
+                if let Some(path) = path {
+                    if let Err(e) = image::image_dimensions(path) {
+                        check.field(false, &field("path"), format!("{}: {e}", path.display()));
+                    }
+                }
+            }
+            Self::Text {
+                text,
+                font,
+                pixel_height,
+                scale,
+                alpha_threshold,
+                ..
+            } => {
+                check.field(
+                    !text.trim().is_empty(),
+                    &field("text"),
+                    "must not be empty".to_string(),
+                );
+                check.field(
+                    *pixel_height > 0.0 && *pixel_height <= 1024.0,
+                    &field("pixel_height"),
+                    format!("must be between 0 and 1024, got {pixel_height}"),
+                );
+                check.field(
+                    *scale > 0.0,
+                    &field("scale"),
+                    format!("must be positive, got {scale}"),
+                );
+                check.field(
+                    (0.0..=1.0).contains(alpha_threshold),
+                    &field("alpha_threshold"),
+                    format!("must be between 0 and 1, got {alpha_threshold}"),
+                );
+                if let Some(font) = font {
+                    check.file(&field("font"), font, &["ttf", "otf"]);
+                }
+            }
+            Self::PointCloud { path, scale } => {
+                check.field(
+                    *scale > 0.0,
+                    &field("scale"),
+                    format!("must be positive, got {scale}"),
+                );
+                check.file(&field("path"), path, &["ply", "xyz"]);
+            }
+            Self::Mesh {
+                path,
+                scale,
+                texture,
+            } => {
+                check.field(
+                    *scale > 0.0,
+                    &field("scale"),
+                    format!("must be positive, got {scale}"),
+                );
+                check.file(&field("path"), path, &["obj"]);
+                if let Some(texture) = texture {
+                    if let Err(e) = image::image_dimensions(texture) {
+                        check.field(
+                            false,
+                            &field("texture"),
+                            format!("{}: {e}", texture.display()),
+                        );
+                    }
+                }
+            }
+            Self::Sphere { radius } => check.field(
+                *radius > 0.0,
+                &field("radius"),
+                format!("must be positive, got {radius}"),
+            ),
+            Self::Cube { size } => check.field(
+                *size > 0.0,
+                &field("size"),
+                format!("must be positive, got {size}"),
+            ),
+            Self::Point => {}
+        }
+    }
+}
+
+/// Collects the errors of the fields under `prefix`.
+struct Checker<'a> {
+    prefix: &'a str,
+    errors: &'a mut Vec<FieldError>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(prefix: &'a str, errors: &'a mut Vec<FieldError>) -> Self {
+        Self { prefix, errors }
+    }
+
+    fn field(&mut self, ok: bool, field: &str, message: String) {
+        if !ok {
+            self.errors.push(FieldError {
+                field: format!("{}{field}", self.prefix),
+                message,
+            });
+        }
+    }
+
+    /// `field` must be an existing file with one of the `extensions`.
+    fn file(&mut self, field: &str, path: &Path, extensions: &[&str]) {
+        let extension = super::point_cloud::extension(path).unwrap_or_default();
+        if !extensions.contains(&extension.as_str()) {
+            self.field(
+                false,
+                field,
+                format!(
+                    "{}: expected a .{} file",
+                    path.display(),
+                    extensions.join(" or .")
+                ),
+            );
+        } else if let Err(e) = std::fs::metadata(path) {
+            self.field(false, field, format!("{}: {e}", path.display()));
+        }
+    }
+}
+
+impl Emission {
+    /// Number of particles to spawn in the frame that goes from `time - dt` to `time`, the
+    /// fractional part of the rate is carried over in `accumulator`.
+    pub fn spawn_count(&self, time: f32, dt: f32, accumulator: &mut f32) -> u32 {
+        *accumulator += self.rate * dt;
+        let spawned = accumulator.floor();
+        *accumulator -= spawned;
+        let mut count = spawned as u64;
+
+        for burst in &self.bursts {
+            // number of times the burst fired before `t`
+            let fired = |t: f32| {
+                if t <= burst.time {
+                    0
+                } else {
+                    match burst.interval {
+                        Some(interval) => ((t - burst.time) / interval).ceil() as u64,
+                        None => 1,
+                    }
+                }
+            };
+            count += (fired(time) - fired(time - dt)) * burst.count as u64;
+        }
+
+        count.min(u32::MAX as u64) as u32
+    }
+}
+
+impl UpdateModule {
+    /// The attribute module implementing the update, `None` for raw wgsl.
+    pub fn attribute(&self) -> Option<Box<dyn Attribute>> {
+        match self {
+            UpdateModule::ScatterReassemble { velocity } => Some(Box::new(ScatterReassemble {
+                velocity: *velocity,
+            })),
+            UpdateModule::Gravity { acceleration } => Some(Box::new(Gravity::new(*acceleration))),
+            UpdateModule::Drag { linear, quadratic } => Some(Box::new(Drag {
+                linear: *linear,
+                quadratic: *quadratic,
+            })),
+            UpdateModule::Attractor {
+                position,
+                strength,
+                radius,
+                falloff,
+            } => Some(Box::new(Attractor::new(
+                *position, *strength, *radius, *falloff,
+            ))),
+            UpdateModule::Vortex {
+                center,
+                axis,
+                strength,
+                radius,
+            } => Some(Box::new(Vortex {
+                center: *center,
+                strength: *strength,
+                axis: *axis,
+                radius: *radius,
+            })),
+            UpdateModule::Turbulence {
+                strength,
+                frequency,
+                speed,
+            } => Some(Box::new(Turbulence {
+                strength: *strength,
+                frequency: *frequency,
+                speed: *speed,
+            })),
+            UpdateModule::Morph {
+                progress,
+                easing,
+                delay,
+                noise,
+            } => Some(Box::new(Morph::new(*progress, *easing, *delay, *noise))),
+            UpdateModule::Boids {
+                separation,
+                alignment,
+                cohesion,
+                view_radius,
+                max_speed,
+                min_speed,
+                avoidance,
+                target,
+                seek,
+            } => Some(Box::new(Boids::new(
+                *target,
+                *seek,
+                *separation,
+                *alignment,
+                *cohesion,
+                *avoidance,
+                *view_radius,
+                *max_speed,
+                *min_speed,
+            ))),
+            UpdateModule::Wgsl(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FieldError {
+    /// path of the field inside the description, e.g. `render.particle_size`.
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    /// The same error for a field nested in `prefix`, e.g. `emitters[0].`.
+    pub fn prefixed(self, prefix: &str) -> Self {
+        Self {
+            field: format!("{prefix}{}", self.field),
+            message: self.message,
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum DescError {
+    Io(PathBuf, std::io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(Vec<FieldError>),
+}
+
+impl fmt::Display for DescError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescError::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            DescError::Parse(e) => write!(f, "{e}"),
+            DescError::Invalid(errors) => {
+                writeln!(f, "invalid scene description:")?;
+                for error in errors {
+                    writeln!(f, "  {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for DescError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_fields(source: &str) -> Vec<String> {
+        match SceneDesc::from_ron(source) {
+            Err(DescError::Invalid(errors)) => errors.into_iter().map(|e| e.field).collect(),
+            other => panic!("expected invalid fields, got {other:?}"),
+        }
+    }
+
+    /// the spawn count of `frames` frames of `dt` seconds.
+    fn spawn_counts(emission: &Emission, dt: f32, frames: u32) -> (Vec<u32>, f32) {
+        let mut accumulator = 0.0;
+        let counts = (1..=frames)
+            .map(|frame| emission.spawn_count(frame as f32 * dt, dt, &mut accumulator))
+            .collect();
+        (counts, accumulator)
+    }
+
+    #[test]
+    fn rate_accumulates_across_frames() {
+        let emission = Emission {
+            rate: 4.0,
+            bursts: vec![],
+        };
+        let (counts, accumulator) = spawn_counts(&emission, 0.125, 8);
+        assert_eq!(counts, [0, 1, 0, 1, 0, 1, 0, 1]);
+        assert_eq!(accumulator, 0.0);
+    }
+
+    #[test]
+    fn fractional_rate_does_not_drift() {
+        let emission = Emission {
+            rate: 0.3,
+            bursts: vec![],
+        };
+        // 0.03 particles per frame for 100 seconds.
+        let (counts, accumulator) = spawn_counts(&emission, 0.1, 1000);
+        assert!(counts.iter().all(|&count| count <= 1));
+        assert!((0.0..1.0).contains(&accumulator));
+        let spawned = counts.iter().sum::<u32>() as f32 + accumulator;
+        assert!((spawned - 30.0).abs() < 1e-3, "{spawned} particles");
+    }
+
+    #[test]
+    fn bursts_fire_once_per_interval() {
+        let emission = Emission {
+            rate: 0.0,
+            bursts: vec![
+                Burst {
+                    time: 0.0,
+                    count: 100,
+                    interval: Some(0.25),
+                },
+                Burst {
+                    time: 0.5,
+                    count: 7,
+                    interval: None,
+                },
+            ],
+        };
+        let (counts, _) = spawn_counts(&emission, 0.125, 16);
+        let mut expected = [100, 0].repeat(8);
+        // the frame going from 0.5 to 0.625.
+        expected[4] += 7;
+        assert_eq!(counts, expected);
+
+        // a frame longer than the interval fires the burst as many times as it repeated.
+        let (counts, _) = spawn_counts(&emission, 1.0, 2);
+        assert_eq!(counts, [407, 400]);
+    }
+
+    #[test]
+    fn builtin_is_valid() {
+        SceneDesc::builtin();
+    }
+
+    #[test]
+    fn negative_drag_is_reported() {
+        let fields = invalid_fields("SceneDesc(emitters: [(update: [Drag(linear: -1.0)])])");
+        assert_eq!(fields, ["emitters[0].update[0].linear"]);
+    }
+
+    #[test]
+    fn zero_vortex_radius_is_reported() {
+        let fields = invalid_fields(
+            "SceneDesc(emitters: [(), (update: [Gravity(acceleration: (0.0, -9.8, 0.0)), \
+             Vortex(strength: 1.0, radius: 0.0)])])",
+        );
+        assert_eq!(fields, ["emitters[1].update[1].radius"]);
+    }
+
+    #[test]
+    fn grid_table_is_capped() {
+        let fields = invalid_fields(&format!(
+            "SceneDesc(emitters: [(ping_pong: true, \
+             grid: Some((cell_size: 1.0, cells: Some({}))))])",
+            MAX_GRID_CELLS + 1
+        ));
+        assert_eq!(fields, ["emitters[0].grid.cells"]);
+    }
+
+    #[test]
+    fn every_bad_field_is_reported() {
+        let fields = invalid_fields(
+            "SceneDesc(emitters: [(update: [Drag(linear: -1.0, quadratic: -1.0), \
+             Vortex(axis: (0.0, 0.0, 0.0), strength: 1.0, radius: -2.0)])])",
+        );
+        assert_eq!(
+            fields,
+            [
+                "emitters[0].update[0].linear",
+                "emitters[0].update[0].quadratic",
+                "emitters[0].update[1].axis",
+                "emitters[0].update[1].radius",
+            ]
+        );
+    }
+}
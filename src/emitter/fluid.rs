@@ -0,0 +1,229 @@
+use crate::sph::FluidParams;
+use crate::uniform::Uniform;
+
+use super::{FluidSettings, GRID_STORAGE_BUFFERS};
+
+/// The fluid of an emitter. Once the grid is built `fluid_density` writes the density of every
+/// live particle of `particles_src` at **@binding(15)**, then `simulate` adds the pressure, the
+/// viscosity and the surface tension of the neighbors to the velocity.
+pub struct Fluid {
+    pub params: Uniform<FluidParams>,
+    /// one density per particle, indexed like the particles.
+    densities: wgpu::Buffer,
+}
+
+/// Storage buffers of the compute shader of an emitter with a fluid, the ones of the grid and
+/// the densities.
+pub const FLUID_STORAGE_BUFFERS: u32 = GRID_STORAGE_BUFFERS + 1;
+
+impl Fluid {
+    pub(super) fn new(
+        device: &wgpu::Device,
+        name: &str,
+        settings: &FluidSettings,
+        capacity: u32,
+    ) -> Self {
+        let mut params = Uniform::<FluidParams>::new(device);
+        params.data = FluidParams::new(settings);
+        Self {
+            params,
+            densities: Self::densities(device, name, capacity),
+        }
+    }
+
+    /// Reallocates the densities for a new capacity.
+    pub(super) fn resize(&mut self, device: &wgpu::Device, name: &str, capacity: u32) {
+        self.densities = Self::densities(device, name, capacity);
+    }
+
+    fn densities(device: &wgpu::Device, name: &str, capacity: u32) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{name} Fluid Densities Buffer")),
+            size: capacity as u64 * 4,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue) {
+        self.params.write(queue);
+    }
+
+    /// The entries of the compute bind groups of the emitter reading the fluid.
+    pub(super) fn entries(&self) -> [wgpu::BindGroupEntry<'_>; 2] {
+        [
+            wgpu::BindGroupEntry {
+                binding: 14,
+                resource: self.params.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 15,
+                resource: self.densities.as_entire_binding(),
+            },
+        ]
+    }
+
+    /// egui controls editing the forces of the fluid.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let params = &mut self.params.data;
+        ui.add(
+            egui::Slider::new(&mut params.rest_density, 0.01..=100.0)
+                .logarithmic(true)
+                .text("rest density"),
+        );
+        ui.add(
+            egui::Slider::new(&mut params.stiffness, 0.0..=10000.0)
+                .logarithmic(true)
+                .text("stiffness"),
+        );
+        ui.add(
+            egui::Slider::new(&mut params.viscosity, 0.0..=10.0)
+                .logarithmic(true)
+                .text("viscosity"),
+        );
+        ui.add(
+            egui::Slider::new(&mut params.surface_tension, 0.0..=10.0)
+                .logarithmic(true)
+                .text("surface tension"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::emitter::{generate_particles, SceneDesc};
+    use crate::particle::Particle;
+    use crate::profiler::Profiler;
+    use crate::sph;
+    use crate::system::{required_limits, System};
+    use crate::texture::Texture;
+
+    const DELTA_TIME: f32 = 1.0 / 120.0;
+
+    /// A device of any backend, the gpu is not compared without one.
+    fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let adapter = pollster::block_on(instance.request_adapter(&Default::default()))?;
+        let device = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                required_limits: required_limits(&adapter),
+                ..Default::default()
+            },
+            None,
+        ))
+        .unwrap();
+        Some(device)
+    }
+
+    /// A fluid after its first step was submitted.
+    fn first_step(device: &wgpu::Device, queue: &wgpu::Queue) -> System {
+        let desc = SceneDesc::from_ron(
+            "SceneDesc(
+                seed: 3,
+                emitters: [(
+                    capacity: 300,
+                    spawn: Cube(size: 3.0),
+                    initial: (direction: (-1.0, 1.0), velocity: (0.0, 2.0)),
+                    update: [],
+                    ping_pong: true,
+                    grid: (cell_size: 1.0),
+                    fluid: (rest_density: 7.0, surface_tension: 0.5),
+                )],
+            )",
+        )
+        .unwrap();
+        let size = (64, 64);
+        let target = Texture::empty(device, size, None).unwrap();
+        let mut system = System::new(device, queue, target.texture.format(), size, &desc).unwrap();
+        system.update(queue, instant::Duration::from_secs_f32(DELTA_TIME));
+        let mut encoder = device.create_command_encoder(&Default::default());
+        system.render(&mut encoder, &target.view, &Profiler::default());
+        queue.submit(Some(encoder.finish()));
+        system
+    }
+
+    /// The particles of the emitter as they were spawned.
+    fn spawned(system: &System) -> Vec<Particle> {
+        let emitter = &system.emitters[0];
+        generate_particles(&emitter.desc, None, emitter.seed).unwrap()
+    }
+
+    fn position(particle: &Particle) -> Vector3<f32> {
+        let [x, y, z, _] = particle.position;
+        Vector3::new(x, y, z)
+    }
+
+    fn velocity(particle: &Particle) -> Vector3<f32> {
+        Vector3::from(particle.dir) * particle.velocity
+    }
+
+    #[test]
+    fn densities_match_the_cpu_reference() {
+        let Some((device, queue)) = device() else {
+            eprintln!("no adapter, the gpu densities are not compared");
+            return;
+        };
+        let system = first_step(&device, &queue);
+
+        // the single step computed the densities of the particles as they were spawned.
+        let fluid = system.emitters[0].fluid.as_ref().unwrap();
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: fluid.densities.size(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(&fluid.densities, 0, &readback, 0, readback.size());
+        queue.submit(Some(encoder.finish()));
+        readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::MaintainBase::wait()).panic_on_timeout();
+        let gpu = bytemuck::cast_slice::<u8, f32>(&readback.slice(..).get_mapped_range()).to_vec();
+
+        let positions = spawned(&system).iter().map(position).collect::<Vec<_>>();
+        let cpu = sph::densities(&positions, &fluid.params.data, 1.0);
+        assert_eq!(gpu.len(), cpu.len());
+        for (i, (gpu, cpu)) in gpu.iter().zip(&cpu).enumerate() {
+            assert!(
+                (gpu - cpu).abs() <= 1e-4 * cpu,
+                "density of the particle {i}: {gpu} on the gpu, {cpu} on the cpu"
+            );
+        }
+    }
+
+    #[test]
+    fn accelerations_match_the_cpu_reference() {
+        let Some((device, queue)) = device() else {
+            eprintln!("no adapter, the gpu accelerations are not compared");
+            return;
+        };
+        let system = first_step(&device, &queue);
+        let emitter = &system.emitters[0];
+        let fluid = emitter.fluid.as_ref().unwrap();
+        let (stepped, alive, _) = emitter.buffers.read(&device, &queue, emitter.current);
+        assert_eq!(alive.len(), stepped.len());
+
+        // without gravity nor update modules the fluid is the only change of the velocity.
+        let spawned = spawned(&system);
+        let positions = spawned.iter().map(position).collect::<Vec<_>>();
+        let velocities = spawned.iter().map(velocity).collect::<Vec<_>>();
+        let densities = sph::densities(&positions, &fluid.params.data, 1.0);
+        let cpu = sph::accelerations(&positions, &velocities, &densities, &fluid.params.data, 1.0);
+        let largest = cpu.iter().map(|a| a.magnitude()).fold(0.0, f32::max);
+        assert!(largest > 0.0);
+        for (i, (stepped, cpu)) in stepped.iter().zip(&cpu).enumerate() {
+            let gpu = (velocity(stepped) - velocities[i]) / DELTA_TIME;
+            assert!(
+                (gpu - cpu).magnitude() <= 1e-5 * largest,
+                "acceleration of the particle {i}: {gpu:?} on the gpu, {cpu:?} on the cpu"
+            );
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::borrow::Cow;
+
+use crate::attr::ShaderBuilder;
+use crate::uniform::Uniform;
+
+use super::{dispatch_size, GridSettings, ParticleBuffers};
+
+/// `GridParams` of the `vfx::grid` module.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GridParams {
+    pub cell_size: f32,
+    /// entries of the hash table.
+    pub cells: u32,
+    _pad: [u32; 2],
+}
+
+/// The spatial hash grid of an emitter. Before `simulate` the live particles of `particles_src`
+/// are counted in the cells of the hash table, a prefix sum gives where every cell starts and
+/// the particles are sorted by cell. The compute shader reads the cells at **@binding(12)** and
+/// the sorted indices at **@binding(13)**, see `neighbors` in `vfx::grid`.
+pub struct Grid {
+    pub params: Uniform<GridParams>,
+    /// the `start` and `end` of every entry of the hash table.
+    cells: wgpu::Buffer,
+    /// indices of the live particles sorted by cell.
+    indices: wgpu::Buffer,
+    /// one per bind group of the emitter, they read the same particles and alive list.
+    bind_groups: [wgpu::BindGroup; 2],
+    bind_group_layout: wgpu::BindGroupLayout,
+    clear_pipeline: wgpu::ComputePipeline,
+    count_pipeline: wgpu::ComputePipeline,
+    scan_pipeline: wgpu::ComputePipeline,
+    scatter_pipeline: wgpu::ComputePipeline,
+    /// entries of the hash table, the capacity when not set.
+    table_size: Option<u32>,
+}
+
+/// Entries of the hash table at most. `grid_scan` runs on a single workgroup of 256 threads that
+/// each sum a slice of the table, the slices stay short up to this size.
+pub const MAX_GRID_CELLS: u32 = 1 << 16;
+
+/// Storage buffers of the compute shader of an emitter with a grid, the ping-pong ones and
+/// the grid tables. wgpu only guarantees 8 of them.
+pub const GRID_STORAGE_BUFFERS: u32 = 9;
+
+impl Grid {
+    pub(super) fn new(
+        device: &wgpu::Device,
+        name: &str,
+        settings: &GridSettings,
+        buffers: &ParticleBuffers,
+    ) -> Self {
+        let module = ShaderBuilder::build_module(include_str!("../shaders/grid.wgsl"));
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
+            label: Some("grid.wgsl"),
+        });
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Grid Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, true),
+                storage(3, false),
+                storage(4, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&format!("Grid {entry_point} pipeline")),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+
+        let mut params = Uniform::<GridParams>::new(device);
+        params.data.cell_size = settings.cell_size;
+        params.data.cells = table_cells(settings.cells, buffers);
+        let (cells, indices, bind_groups) =
+            Self::buffers(device, name, &bind_group_layout, &params, buffers);
+        Self {
+            params,
+            cells,
+            indices,
+            bind_groups,
+            bind_group_layout,
+            clear_pipeline: create_pipeline("grid_clear"),
+            count_pipeline: create_pipeline("grid_count"),
+            scan_pipeline: create_pipeline("grid_scan"),
+            scatter_pipeline: create_pipeline("grid_scatter"),
+            table_size: settings.cells,
+        }
+    }
+
+    /// Reallocates the tables for the particle `buffers` of a new capacity.
+    pub(super) fn resize(&mut self, device: &wgpu::Device, name: &str, buffers: &ParticleBuffers) {
+        self.params.data.cells = table_cells(self.table_size, buffers);
+        (self.cells, self.indices, self.bind_groups) =
+            Self::buffers(device, name, &self.bind_group_layout, &self.params, buffers);
+    }
+
+    /// The tables sized by `params` and the capacity of `buffers`, and the bind groups reading
+    /// their particles.
+    fn buffers(
+        device: &wgpu::Device,
+        name: &str,
+        layout: &wgpu::BindGroupLayout,
+        params: &Uniform<GridParams>,
+        buffers: &ParticleBuffers,
+    ) -> (wgpu::Buffer, wgpu::Buffer, [wgpu::BindGroup; 2]) {
+        let storage = |label: &str, size: u64| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(&format!("{name} {label}")),
+                size,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            })
+        };
+        let cells = storage("Grid Cells Buffer", params.data.cells as u64 * 8);
+        let indices = storage("Grid Indices Buffer", buffers.capacity() as u64 * 4);
+
+        // the bind group `i` of the emitter simulates from these particles.
+        let other = buffers
+            .ping_pong
+            .as_ref()
+            .expect("the grid needs ping-pong");
+        let sources = [&buffers.particles, other];
+        let bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: buffers.alive_lists[i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: sources[i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: cells.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: indices.as_entire_binding(),
+                    },
+                ],
+                label: Some(&format!("{name} Grid Bind Group {i}")),
+            })
+        });
+        (cells, indices, bind_groups)
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue) {
+        self.params.write(queue);
+    }
+
+    /// Sorts the live particles of the bind group `current`, the `simulate` dispatch arguments
+    /// at the start of `indirect_buffer` must be ready. It leaves its own bind group at 0.
+    pub fn build(
+        &self,
+        cpass: &mut wgpu::ComputePass,
+        current: usize,
+        indirect_buffer: &wgpu::Buffer,
+    ) {
+        cpass.set_bind_group(0, &self.bind_groups[current], &[]);
+
+        cpass.set_pipeline(&self.clear_pipeline);
+        let (x, y) = dispatch_size(self.params.data.cells);
+        cpass.dispatch_workgroups(x, y, 1);
+
+        cpass.set_pipeline(&self.count_pipeline);
+        cpass.dispatch_workgroups_indirect(indirect_buffer, 0);
+
+        cpass.set_pipeline(&self.scan_pipeline);
+        cpass.dispatch_workgroups(1, 1, 1);
+
+        cpass.set_pipeline(&self.scatter_pipeline);
+        cpass.dispatch_workgroups_indirect(indirect_buffer, 0);
+    }
+
+    /// The entries of the compute bind groups of the emitter reading the grid.
+    pub(super) fn entries(&self) -> [wgpu::BindGroupEntry<'_>; 3] {
+        [
+            wgpu::BindGroupEntry {
+                binding: 11,
+                resource: self.params.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 12,
+                resource: self.cells.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 13,
+                resource: self.indices.as_entire_binding(),
+            },
+        ]
+    }
+
+    /// egui controls editing the cell size, it stays at least `reach` so the 27 cells around a
+    /// particle hold every neighbor the update modules look for.
+    pub fn ui(&mut self, ui: &mut egui::Ui, reach: f32) {
+        let min = reach.max(0.01);
+        let cell_size = &mut self.params.data.cell_size;
+        *cell_size = cell_size.max(min);
+        ui.add(
+            egui::Slider::new(cell_size, min..=min.max(10.0))
+                .logarithmic(true)
+                .text("grid cell size"),
+        );
+    }
+}
+
+/// Entries of the hash table, `table_size` or the capacity of `buffers` up to [`MAX_GRID_CELLS`].
+fn table_cells(table_size: Option<u32>, buffers: &ParticleBuffers) -> u32 {
+    table_size.unwrap_or(buffers.capacity().min(MAX_GRID_CELLS))
+}
@@ -0,0 +1,155 @@
+use std::borrow::Cow;
+
+use image::{DynamicImage, GenericImageView};
+use wgpu::util::DeviceExt;
+
+use super::{EmitterDesc, ImageGrid, ImageOptions};
+use crate::attr::ShaderBuilder;
+use crate::texture::Texture;
+
+/// `LoaderParams` of `load_data_from_image.wgsl`.
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct LoaderParams {
+    grid: [u32; 2],
+    size: [f32; 2],
+    stride: u32,
+    alpha_threshold: f32,
+    depth: f32,
+    scale: f32,
+    keep_color: u32,
+    keep_target: u32,
+}
+
+/// Fills the particles of an image emitter on the gpu, the counterpart of
+/// [`image_seeds`](super::image_seeds): the image is uploaded as a [`Texture`] and every cell of
+/// its [`ImageGrid`] is read by a compute shader, the mean of its texels when it resamples.
+///
+/// The kept pixels are written to the front of `particles`, only their position, origin, color
+/// (unless the `desc` sets `initial.color`) and morph target (unless it has a `target` shape) are
+/// touched. They are counted in `list`, the alive or dead list the
+/// particles start in, its count must be 0.
+pub fn load_image_gpu(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    img: &DynamicImage,
+    options: &ImageOptions,
+    desc: &EmitterDesc,
+    particles: &wgpu::Buffer,
+    list: &wgpu::Buffer,
+) {
+    let texture = Texture::data_from_image(device, queue, img, Some("Loader texture"))
+        .expect("Failed to upload image");
+
+    let grid = ImageGrid::new(img.dimensions(), options);
+    let size = match grid.stride {
+        0 => (grid.width, grid.height),
+        _ => img.dimensions(),
+    };
+    let params = LoaderParams {
+        grid: [grid.width, grid.height],
+        size: [size.0 as f32, size.1 as f32],
+        stride: grid.stride,
+        alpha_threshold: options.alpha_threshold,
+        depth: options.depth,
+        scale: options.scale,
+        keep_color: desc.initial.color.is_some() as u32,
+        keep_target: desc.target.is_some() as u32,
+    };
+    let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Loader Params Buffer"),
+        contents: bytemuck::bytes_of(&params),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
+    let module = ShaderBuilder::build_module(include_str!("../shaders/load_data_from_image.wgsl"));
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
+        label: Some("load_data_from_image.wgsl"),
+    });
+
+    let storage = wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Storage { read_only: false },
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    };
+    let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty,
+        count: None,
+    };
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            entry(
+                0,
+                wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            ),
+            entry(
+                1,
+                wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+            ),
+            entry(2, storage),
+            entry(3, storage),
+        ],
+        label: Some("Loader Bind Group Layout"),
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: particles.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: list.as_entire_binding(),
+            },
+        ],
+        label: Some("Loader Bind Group"),
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Loader Pipeline Layout"),
+        bind_group_layouts: &[&layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Particles loader pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("loader"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Loader Encoder"),
+    });
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Loader pass"),
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(grid.width.div_ceil(8), grid.height.div_ceil(8), 1);
+    }
+    queue.submit(Some(encoder.finish()));
+}
@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use cgmath::{InnerSpace, Vector3};
+use image::RgbaImage;
+use rand::Rng;
+
+use super::Seed;
+
+/// A triangle of the mesh with everything needed to color a point on it.
+struct Triangle {
+    positions: [[f32; 3]; 3],
+    colors: Option<[[f32; 3]; 3]>,
+    texcoords: Option<[[f32; 2]; 3]>,
+    /// index in `textures` of the diffuse texture of its material.
+    texture: Option<usize>,
+    /// diffuse color of its material.
+    diffuse: [f32; 3],
+}
+
+/// Samples `count` points uniformly on the surface of an `.obj` mesh: each triangle gets a share
+/// of the points proportional to its area.
+///
+/// A point takes the interpolated vertex colors when the mesh has some, otherwise it looks up
+/// `texture` (or the diffuse texture of its material) at its uv, otherwise it takes the diffuse
+/// color of its material.
+pub fn sample_mesh(
+    path: &Path,
+    texture: Option<&Path>,
+    count: u32,
+    scale: f32,
+    rng: &mut impl Rng,
+) -> Result<Vec<Seed>> {
+    let (models, materials) = tobj::load_obj(
+        path,
+        &tobj::LoadOptions {
+            single_index: true,
+            triangulate: true,
+            ignore_points: true,
+            ignore_lines: true,
+        },
+    )
+    .with_context(|| path.display().to_string())?;
+    // a missing .mtl file is not an error, the mesh is just uncolored.
+    let materials = materials.unwrap_or_default();
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let load = |path: &Path| -> Result<RgbaImage> {
+        Ok(image::open(path)
+            .with_context(|| path.display().to_string())?
+            .to_rgba8())
+    };
+    let mut textures = vec![];
+    if let Some(texture) = texture {
+        textures.push(load(texture)?);
+    }
+    let material_textures = materials
+        .iter()
+        .map(|material| match (&material.diffuse_texture, texture) {
+            (Some(name), None) => {
+                textures.push(load(&dir.join(name))?);
+                Ok(Some(textures.len() - 1))
+            }
+            (_, Some(_)) => Ok(Some(0)),
+            (None, None) => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut triangles = vec![];
+    for model in &models {
+        let mesh = &model.mesh;
+        let diffuse = mesh
+            .material_id
+            .and_then(|id| materials.get(id)?.diffuse)
+            .unwrap_or([1.0; 3]);
+        let texture = match mesh.material_id {
+            Some(id) => material_textures.get(id).copied().flatten(),
+            None => texture.map(|_| 0),
+        };
+
+        for face in mesh.indices.chunks_exact(3) {
+            let Some(positions) = corners::<3>(&mesh.positions, face) else {
+                continue;
+            };
+            triangles.push(Triangle {
+                positions: positions.map(|p| p.map(|c| c * scale)),
+                colors: corners(&mesh.vertex_color, face),
+                texcoords: corners(&mesh.texcoords, face),
+                texture,
+                diffuse,
+            });
+        }
+    }
+
+    let mut total = 0.0;
+    let areas = triangles
+        .iter()
+        .map(|triangle| {
+            total += area(&triangle.positions);
+            total
+        })
+        .collect::<Vec<_>>();
+    anyhow::ensure!(total > 0.0, "{}: the mesh has no surface", path.display());
+
+    Ok((0..count)
+        .map(|_| {
+            let target = rng.gen::<f32>() * total;
+            let i = areas.partition_point(|area| *area <= target);
+            let triangle = &triangles[i.min(triangles.len() - 1)];
+
+            // uniform barycentric coordinates.
+            let (r1, r2) = (rng.gen::<f32>().sqrt(), rng.gen::<f32>());
+            let weights = [1.0 - r1, r1 * (1.0 - r2), r1 * r2];
+
+            let origin = mix(&triangle.positions, weights);
+            let color = match (&triangle.colors, &triangle.texcoords, triangle.texture) {
+                (Some(colors), _, _) => {
+                    let [r, g, b] = mix(colors, weights);
+                    [r, g, b, 1.0]
+                }
+                (None, Some(uvs), Some(texture)) => texel(&textures[texture], mix(uvs, weights)),
+                _ => {
+                    let [r, g, b] = triangle.diffuse;
+                    [r, g, b, 1.0]
+                }
+            };
+            Seed { origin, color }
+        })
+        .collect())
+}
+
+/// The attribute of the 3 vertices of `face`, `None` when the mesh does not have it.
+fn corners<const N: usize>(data: &[f32], face: &[u32]) -> Option<[[f32; N]; 3]> {
+    let mut corners = [[0.0; N]; 3];
+    for (corner, i) in corners.iter_mut().zip(face) {
+        let i = *i as usize * N;
+        corner.copy_from_slice(data.get(i..i + N)?);
+    }
+    Some(corners)
+}
+
+fn mix<const N: usize>(corners: &[[f32; N]; 3], weights: [f32; 3]) -> [f32; N] {
+    std::array::from_fn(|c| (0..3).map(|i| corners[i][c] * weights[i]).sum())
+}
+
+fn area(positions: &[[f32; 3]; 3]) -> f32 {
+    let [a, b, c] = positions.map(Vector3::from);
+    (b - a).cross(c - a).magnitude() / 2.0
+}
+
+/// The nearest texel at `uv`, wrapping around like a repeat sampler. The v axis of obj files
+/// points up.
+fn texel(texture: &RgbaImage, [u, v]: [f32; 2]) -> [f32; 4] {
+    let (width, height) = texture.dimensions();
+    let x = (u.rem_euclid(1.0) * width as f32) as u32;
+    let y = ((1.0 - v.rem_euclid(1.0)) * height as f32) as u32;
+    let pixel = texture.get_pixel(x.min(width - 1), y.min(height - 1));
+    pixel.0.map(|c| c as f32 / 255.0)
+}
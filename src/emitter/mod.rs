@@ -0,0 +1,1174 @@
+pub mod desc;
+pub mod fluid;
+pub mod grid;
+pub mod loader;
+pub mod mesh;
+pub mod point_cloud;
+pub mod source;
+pub mod text;
+
+pub use desc::*;
+pub use fluid::*;
+pub use grid::*;
+pub use loader::*;
+pub use mesh::*;
+pub use point_cloud::*;
+pub use source::*;
+pub use text::*;
+
+use core::f32;
+use std::borrow::Cow;
+
+use cgmath::{InnerSpace, Vector3};
+use image::{DynamicImage, GenericImageView};
+use rand::Rng;
+use wgpu::util::DeviceExt;
+
+use crate::attr::{AttrContext, Attributes, ShaderBuilder};
+use crate::collider::ColliderUniform;
+use crate::particle::Particle;
+use crate::pcg::Pcg;
+use crate::system::{
+    create_compute_bind_group_layout, create_depth_pipeline, create_render_pipeline, SceneBindings,
+};
+use crate::timestep::MAX_SUBSTEPS;
+use crate::uniform::Uniform;
+
+/// An emitter owns its particles and everything needed to simulate and draw them, the
+/// [`System`](crate::system::System) only shares the camera and the global simulation buffers
+/// between emitters.
+pub struct Emitter {
+    pub name: String,
+    pub particle_uniform: Uniform<AttrContext>,
+    /// the update modules, bound at **@group(2)** of the compute pipelines.
+    pub attributes: Attributes,
+    /// the colliders of the emitter, bound at **@group(3)** of the compute pipelines.
+    pub colliders: Uniform<ColliderUniform>,
+    /// sorts the particles by cell before every step, see [`EmitterDesc::grid`].
+    pub grid: Option<Grid>,
+    /// the liquid simulated over the grid, see [`EmitterDesc::fluid`].
+    pub fluid: Option<Fluid>,
+    render_uniform: Uniform<RenderUniform>,
+    emit_pipeline: wgpu::ComputePipeline,
+    prepare_simulate_pipeline: wgpu::ComputePipeline,
+    compute_pipeline: wgpu::ComputePipeline,
+    prepare_draw_pipeline: wgpu::ComputePipeline,
+    /// computes the densities of the fluid before `simulate`.
+    density_pipeline: Option<wgpu::ComputePipeline>,
+    render_pipeline: wgpu::RenderPipeline,
+    /// draws the particles in the depth prepass, for the occluders only.
+    depth_pipeline: Option<wgpu::RenderPipeline>,
+    buffers: ParticleBuffers,
+    /// the `DispatchIndirectArgs` of `simulate` followed by the `DrawIndirectArgs`.
+    indirect_buffer: wgpu::Buffer,
+    /// copy of `instance_count` read back after the frame.
+    stats_buffer: wgpu::Buffer,
+    /// the mapping of `stats_buffer` in flight, no new count is copied until it's read.
+    stats_mapping: Option<flume::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+    /// holds the *particles buffer* at **@binding(0)** \
+    /// holds the *simulation params buffer* at **@binding(1)** \
+    /// holds the *dead list buffer* at **@binding(3)** \
+    /// holds the *alive list buffers* at **@binding(4)** and **@binding(5)**, swapped every frame \
+    /// holds the *indirect buffer* at **@binding(6)** \
+    /// holds the *draw buffer* at **@binding(7)** \
+    /// holds the *source particles buffer* at **@binding(8)** in ping-pong, swapped with
+    /// **@binding(0)** every step \
+    /// holds the *camera buffer* and the *depth prepass* at **@binding(9)** and **@binding(10)**
+    /// with a `Depth` collider \
+    /// holds the *grid params and tables* from **@binding(11)** to **@binding(13)** with a grid \
+    /// holds the *fluid params* and the *densities buffer* at **@binding(14)** and
+    /// **@binding(15)** with a fluid
+    bind_groups: [wgpu::BindGroup; 2],
+    /// index of the bind group used by the next frame.
+    current: usize,
+    capacity: u32,
+    /// particles drawn in the last frame.
+    pub alive_count: u32,
+    emission: Option<Emission>,
+    spawn_accumulator: f32,
+    /// the emitter uniform of every step simulated in the frame.
+    steps: Vec<AttrContext>,
+    /// `steps` staged on the gpu, each step copies its entry to `particle_uniform`.
+    steps_buffer: wgpu::Buffer,
+    time: f32,
+    /// the description the emitter was built from, it spawns the particles added by
+    /// [`Emitter::set_capacity`].
+    desc: EmitterDesc,
+    seed: u32,
+}
+
+impl Emitter {
+    /// Fails with the field of `desc` that can't be built, its path is relative to the emitter:
+    /// update modules that don't compose, a spawn shape whose file can't be loaded, more particles
+    /// than the device can hold or a grid needing more storage buffers than it has.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        desc: &EmitterDesc,
+        seed: u32,
+        camera_layout: &wgpu::BindGroupLayout,
+        scene: &SceneBindings,
+    ) -> Result<Self, FieldError> {
+        let mut render_uniform = Uniform::<RenderUniform>::new(device);
+        render_uniform.data.particle_size = desc.render.particle_size;
+
+        let module = ShaderBuilder::build_module(include_str!("../shaders/vfx_render.wgsl"));
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
+            label: Some("vfx_render.wgsl"),
+        });
+
+        let mut builder = ShaderBuilder::new(include_str!("../shaders/vfx_compute.wgsl"));
+        let mut modules = vec![];
+        for module in &desc.update {
+            match module.attribute() {
+                Some(attribute) => {
+                    builder.attribute(attribute.as_ref());
+                    modules.push(attribute);
+                }
+                None => {
+                    if let UpdateModule::Wgsl(code) = module {
+                        builder.code(code);
+                    }
+                }
+            }
+        }
+        if desc.ping_pong {
+            builder.define("PING_PONG");
+        }
+        if desc.depth_collision() {
+            builder.define("DEPTH_COLLISION");
+        }
+        if desc.grid.is_some() {
+            builder.define("GRID");
+        }
+        if desc.fluid.is_some() {
+            builder.define("FLUID");
+        }
+        let module = builder.build().map_err(|message| FieldError {
+            field: "update".to_string(),
+            message,
+        })?;
+        let attributes = Attributes::new(device, modules);
+
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
+            label: Some("vfx_compute.wgsl"),
+        });
+
+        let image = spawn_image(&desc.spawn, desc.capacity, "spawn")?;
+        let gpu_image = image.as_ref().filter(|image| image.gpu);
+        let particles = generate_particles(desc, image.as_ref(), seed)?;
+        let capacity = particles.len() as u32;
+        let max = max_capacity(&device.limits());
+        if capacity == 0 || capacity > max {
+            return Err(FieldError {
+                field: "capacity".to_string(),
+                message: match capacity {
+                    0 => "the spawn shape produced no particles".to_string(),
+                    _ => format!(
+                        "{capacity} particles don't fit in the buffers of this device, the \
+                         maximum is {max}"
+                    ),
+                },
+            });
+        }
+
+        // without emission every particle starts alive, otherwise they all start in the dead list.
+        let indices = (0..capacity).collect::<Vec<u32>>();
+        let (dead, alive) = match desc.emission {
+            Some(_) => (indices.as_slice(), &[][..]),
+            None => (&[][..], indices.as_slice()),
+        };
+        let buffers =
+            ParticleBuffers::new(device, &desc.name, &particles, dead, alive, desc.ping_pong);
+        if let Some(SpawnImage { img, options, .. }) = gpu_image {
+            let list = match desc.emission {
+                Some(_) => &buffers.dead_list,
+                None => &buffers.alive_lists[0],
+            };
+            // an image loaded on the gpu counts the pixels it keeps itself.
+            queue.write_buffer(list, 0, bytemuck::bytes_of(&0u32));
+            load_image_gpu(device, queue, img, options, desc, &buffers.particles, list);
+            buffers.copy_to_ping_pong(device, queue);
+        }
+        let indirect_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Indirect Buffer", desc.name)),
+            contents: bytemuck::cast_slice(&[0u32, 1, 1, 6, 0, 0, 0]),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+        });
+        let stats_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{} Stats Buffer", desc.name)),
+            size: 4,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let storage_buffers = match (&desc.grid, &desc.fluid) {
+            (_, Some(_)) => Some(("fluid", FLUID_STORAGE_BUFFERS)),
+            (Some(_), None) => Some(("grid", GRID_STORAGE_BUFFERS)),
+            (None, None) => None,
+        };
+        if let Some((mode, needed)) = storage_buffers {
+            let max = device.limits().max_storage_buffers_per_shader_stage;
+            if max < needed {
+                return Err(FieldError {
+                    field: mode.to_string(),
+                    message: format!(
+                        "needs {needed} storage buffers per shader stage, the device has {max}"
+                    ),
+                });
+            }
+        }
+        let grid = desc
+            .grid
+            .as_ref()
+            .map(|settings| Grid::new(device, &desc.name, settings, &buffers));
+        let fluid = desc
+            .fluid
+            .as_ref()
+            .map(|settings| Fluid::new(device, &desc.name, settings, capacity));
+        let compute_layout = create_compute_bind_group_layout(
+            device,
+            desc.ping_pong,
+            desc.depth_collision(),
+            grid.is_some(),
+            fluid.is_some(),
+        );
+        let bind_groups = buffers.bind_groups(
+            device,
+            &desc.name,
+            scene,
+            &indirect_buffer,
+            desc.depth_collision(),
+            grid.as_ref(),
+            fluid.as_ref(),
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Emitter render pipeline layout"),
+            bind_group_layouts: &[camera_layout, &render_uniform.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline =
+            create_render_pipeline(device, &shader, format, desc.render.blend, &pipeline_layout);
+        let depth_pipeline = desc
+            .render
+            .occluder
+            .then(|| create_depth_pipeline(device, &shader, &pipeline_layout));
+
+        let mut particle_uniform = Uniform::<AttrContext>::new(device);
+        let steps_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{} Steps Buffer", desc.name)),
+            size: (MAX_SUBSTEPS as usize * size_of::<AttrContext>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        particle_uniform.data.seed = seed;
+        let initial = &mut particle_uniform.data.initial;
+        if let Some(color) = desc.initial.color {
+            initial.color = color;
+        }
+        initial.direction = desc.initial.direction.into();
+        initial.velocity = desc.initial.velocity.into();
+        if let Some((min, max)) = desc.initial.lifetime {
+            particle_uniform.data.lifetime.min = min;
+            particle_uniform.data.lifetime.max = max;
+        }
+        let mut colliders = Uniform::<ColliderUniform>::new(device);
+        colliders.data = ColliderUniform::new(&desc.colliders);
+        colliders.write(queue);
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute Pipeline Layout"),
+                bind_group_layouts: &[
+                    &compute_layout,
+                    &particle_uniform.bind_group_layout,
+                    &attributes.bind_group_layout,
+                    &colliders.bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let create_compute_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&format!("Particles {entry_point} pipeline")),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+
+        Ok(Self {
+            name: desc.name.clone(),
+            particle_uniform,
+            attributes,
+            colliders,
+            grid,
+            render_uniform,
+            emit_pipeline: create_compute_pipeline("emit"),
+            prepare_simulate_pipeline: create_compute_pipeline("prepare_simulate"),
+            compute_pipeline: create_compute_pipeline("simulate"),
+            prepare_draw_pipeline: create_compute_pipeline("prepare_draw"),
+            density_pipeline: fluid
+                .is_some()
+                .then(|| create_compute_pipeline("fluid_density")),
+            fluid,
+            render_pipeline,
+            depth_pipeline,
+            buffers,
+            indirect_buffer,
+            stats_buffer,
+            stats_mapping: None,
+            bind_groups,
+            current: 0,
+            capacity,
+            alive_count: alive.len() as u32,
+            emission: desc.emission.clone(),
+            spawn_accumulator: 0.0,
+            steps: vec![],
+            steps_buffer,
+            time: 0.0,
+            desc: desc.clone(),
+            seed,
+        })
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// The emitter reads the depth prepass, see [`EmitterDesc::depth_collision`].
+    pub fn depth_collision(&self) -> bool {
+        self.desc.depth_collision()
+    }
+
+    /// Computes how many particles are spawned in each of the `steps` of this frame.
+    pub fn update(&mut self, steps: u32, dt: f32) {
+        self.steps.clear();
+        for _ in 0..steps {
+            self.time += dt;
+            let mut step = self.particle_uniform.data;
+            step.spawn.count = match &self.emission {
+                Some(emission) => emission.spawn_count(self.time, dt, &mut self.spawn_accumulator),
+                None => 0,
+            };
+            self.steps.push(step);
+        }
+    }
+
+    /// `alpha` interpolates the drawn particles between the last two steps.
+    pub fn write(&mut self, queue: &wgpu::Queue, alpha: f32) {
+        queue.write_buffer(&self.steps_buffer, 0, bytemuck::cast_slice(&self.steps));
+        self.attributes.write(queue);
+        self.colliders.write(queue);
+        if let Some(grid) = &self.grid {
+            grid.write(queue);
+        }
+        if let Some(fluid) = &self.fluid {
+            fluid.write(queue);
+        }
+        self.render_uniform.data.alpha = alpha;
+        self.render_uniform.write(queue);
+    }
+
+    /// Copies the uniform of the `step`, it must be encoded before the compute pass that
+    /// simulates it.
+    pub fn prepare_step(&self, encoder: &mut wgpu::CommandEncoder, step: usize) {
+        let size = size_of::<AttrContext>() as wgpu::BufferAddress;
+        encoder.copy_buffer_to_buffer(
+            &self.steps_buffer,
+            step as wgpu::BufferAddress * size,
+            &self.particle_uniform.buffer,
+            0,
+            size,
+        );
+    }
+
+    /// Emits the new particles and simulates the live ones, only the workgroups needed by the
+    /// alive list are dispatched. The alive lists are swapped for the next step.
+    pub fn simulate(&mut self, cpass: &mut wgpu::ComputePass, step: usize) {
+        self.set_bind_groups(cpass);
+
+        // in ping-pong the new particles are written after the others were simulated, see
+        // `EmitterDesc::ping_pong`.
+        let ping_pong = self.desc.ping_pong;
+        if !ping_pong {
+            self.emit(cpass, step);
+        }
+
+        cpass.set_pipeline(&self.prepare_simulate_pipeline);
+        cpass.dispatch_workgroups(1, 1, 1);
+
+        if let Some(grid) = &self.grid {
+            grid.build(cpass, self.current, &self.indirect_buffer);
+            self.set_bind_groups(cpass);
+        }
+
+        if let Some(density_pipeline) = &self.density_pipeline {
+            cpass.set_pipeline(density_pipeline);
+            cpass.dispatch_workgroups_indirect(&self.indirect_buffer, 0);
+        }
+
+        cpass.set_pipeline(&self.compute_pipeline);
+        cpass.dispatch_workgroups_indirect(&self.indirect_buffer, 0);
+
+        if ping_pong {
+            self.emit(cpass, step);
+        }
+
+        cpass.set_pipeline(&self.prepare_draw_pipeline);
+        cpass.dispatch_workgroups(1, 1, 1);
+
+        self.current = 1 - self.current;
+    }
+
+    fn set_bind_groups(&self, cpass: &mut wgpu::ComputePass) {
+        cpass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+        cpass.set_bind_group(1, &self.particle_uniform.bind_group, &[]);
+        cpass.set_bind_group(2, &self.attributes.bind_group, &[]);
+        cpass.set_bind_group(3, &self.colliders.bind_group, &[]);
+    }
+
+    fn emit(&self, cpass: &mut wgpu::ComputePass, step: usize) {
+        let spawn_count = self.steps[step].spawn.count;
+        if spawn_count > 0 {
+            cpass.set_pipeline(&self.emit_pipeline);
+            let (x, y) = dispatch_size(spawn_count.min(self.capacity));
+            cpass.dispatch_workgroups(x, y, 1);
+        }
+    }
+
+    /// Draws the live particles as instances of the `vertex_buffer` quad.
+    pub fn draw(
+        &self,
+        rpass: &mut wgpu::RenderPass,
+        camera_bind_group: &wgpu::BindGroup,
+        vertex_buffer: &wgpu::Buffer,
+    ) {
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, camera_bind_group, &[]);
+        rpass.set_bind_group(1, &self.render_uniform.bind_group, &[]);
+        rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        rpass.set_vertex_buffer(1, self.buffers.draw.slice(..));
+        rpass.draw_indirect(&self.indirect_buffer, DRAW_ARGS_OFFSET);
+    }
+
+    /// Draws the depth of the live particles when the emitter is an occluder.
+    pub fn draw_depth(
+        &self,
+        rpass: &mut wgpu::RenderPass,
+        camera_bind_group: &wgpu::BindGroup,
+        vertex_buffer: &wgpu::Buffer,
+    ) {
+        let Some(depth_pipeline) = &self.depth_pipeline else {
+            return;
+        };
+        rpass.set_pipeline(depth_pipeline);
+        rpass.set_bind_group(0, camera_bind_group, &[]);
+        rpass.set_bind_group(1, &self.render_uniform.bind_group, &[]);
+        rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        rpass.set_vertex_buffer(1, self.buffers.draw.slice(..));
+        rpass.draw_indirect(&self.indirect_buffer, DRAW_ARGS_OFFSET);
+    }
+
+    /// Copies the instance count of the frame so [`Emitter::read_stats`] can read it, unless the
+    /// count of an earlier frame is still being read.
+    pub fn copy_stats(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.stats_mapping.is_some() {
+            return;
+        }
+        // instance_count
+        encoder.copy_buffer_to_buffer(
+            &self.indirect_buffer,
+            DRAW_ARGS_OFFSET + 4,
+            &self.stats_buffer,
+            0,
+            4,
+        );
+    }
+
+    /// Reallocates the particle buffers for `capacity` particles, at most [`max_capacity`], and
+    /// returns the capacity applied.
+    ///
+    /// The particles are read back and packed, the live ones first, so a smaller capacity drops
+    /// the dead particles before the live ones. A larger one adds particles of the spawn shape
+    /// sampled for the new capacity, an image or a point cloud may not have that many and the
+    /// capacity is then lower. The pipelines don't depend on the capacity, only the bind groups
+    /// are rebuilt.
+    ///
+    /// It waits for the gpu, call it between frames once the last one was submitted. The emitter
+    /// is left as it was when the capacity doesn't fit or the spawn shape can't be loaded anymore.
+    pub fn set_capacity(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        capacity: u32,
+        scene: &SceneBindings,
+    ) -> Result<u32, FieldError> {
+        let max = max_capacity(&device.limits());
+        if capacity == 0 || capacity > max {
+            return Err(FieldError {
+                field: "capacity".to_string(),
+                message: match capacity {
+                    0 => "must be positive".to_string(),
+                    _ => format!(
+                        "{capacity} particles don't fit in the buffers of this device, the \
+                         maximum is {max}"
+                    ),
+                },
+            });
+        }
+        let (particles, alive, dead) = self.buffers.read(device, queue, self.current);
+        let alive = alive
+            .iter()
+            .map(|&i| particles[i as usize])
+            .take(capacity as usize)
+            .collect::<Vec<_>>();
+        let dead = dead
+            .iter()
+            .map(|&i| particles[i as usize])
+            .take(capacity as usize - alive.len())
+            .collect::<Vec<_>>();
+
+        let kept = alive.len() + dead.len();
+        let mut spawned = vec![];
+        if kept < capacity as usize {
+            // the gpu loader would overwrite the live particles.
+            let image = spawn_image(&self.desc.spawn, capacity, "spawn")?.map(|image| SpawnImage {
+                gpu: false,
+                ..image
+            });
+            let desc = EmitterDesc {
+                capacity,
+                ..self.desc.clone()
+            };
+            spawned = generate_particles(&desc, image.as_ref(), self.seed)?;
+            spawned.drain(..kept.min(spawned.len()));
+        }
+
+        // the new particles start like the ones of `Emitter::new`.
+        let (alive, dead) = match self.emission {
+            Some(_) => (alive, [dead, spawned].concat()),
+            None => ([alive, spawned].concat(), dead),
+        };
+        let particles = [alive.as_slice(), &dead].concat();
+        let capacity = particles.len() as u32;
+        let indices = (0..particles.len() as u32).collect::<Vec<_>>();
+        let (alive_list, dead_list) = indices.split_at(alive.len());
+        self.buffers = ParticleBuffers::new(
+            device,
+            &self.name,
+            &particles,
+            dead_list,
+            alive_list,
+            self.desc.ping_pong,
+        );
+        if let Some(grid) = &mut self.grid {
+            grid.resize(device, &self.name, &self.buffers);
+        }
+        if let Some(fluid) = &mut self.fluid {
+            fluid.resize(device, &self.name, self.buffers.capacity());
+        }
+        self.bind_groups = self.buffers.bind_groups(
+            device,
+            &self.name,
+            scene,
+            &self.indirect_buffer,
+            self.desc.depth_collision(),
+            self.grid.as_ref(),
+            self.fluid.as_ref(),
+        );
+        self.current = 0;
+        self.capacity = capacity;
+        self.desc.capacity = capacity;
+        self.alive_count = alive.len() as u32;
+
+        // the live particles are drawn as they are until the next step packs them again.
+        queue.write_buffer(&self.buffers.draw, 0, bytemuck::cast_slice(&alive));
+        queue.write_buffer(
+            &self.indirect_buffer,
+            DRAW_ARGS_OFFSET + 4,
+            bytemuck::bytes_of(&self.alive_count),
+        );
+        Ok(capacity)
+    }
+
+    /// Starts reading back the instance count copied by the last frame, it must be called once
+    /// the frame was submitted. [`Emitter::read_stats`] picks it up once the device was polled.
+    pub fn map_stats(&mut self) {
+        if self.stats_mapping.is_some() {
+            return;
+        }
+        let (tx, rx) = flume::bounded(1);
+        self.stats_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = tx.send(result);
+            });
+        self.stats_mapping = Some(rx);
+    }
+
+    /// Updates [`Emitter::alive_count`] when the mapping started by [`Emitter::map_stats`] is
+    /// done, it never waits for the gpu.
+    pub fn read_stats(&mut self) {
+        let Some(mapping) = &self.stats_mapping else {
+            return;
+        };
+        match mapping.try_recv() {
+            Ok(Ok(())) => {
+                let slice = self.stats_buffer.slice(..);
+                self.alive_count = bytemuck::cast_slice::<u8, u32>(&slice.get_mapped_range())[0];
+                self.stats_buffer.unmap();
+            }
+            Err(flume::TryRecvError::Empty) => return,
+            // a failed mapping is started again after the next frame.
+            Ok(Err(_)) | Err(flume::TryRecvError::Disconnected) => {}
+        }
+        self.stats_mapping = None;
+    }
+}
+
+/// The buffers sized by the capacity of an emitter.
+struct ParticleBuffers {
+    /// the particles, read by the bind group 0 in ping-pong.
+    particles: wgpu::Buffer,
+    /// second copy of the particles in ping-pong, the steps swap the two.
+    ping_pong: Option<wgpu::Buffer>,
+    dead_list: wgpu::Buffer,
+    alive_lists: [wgpu::Buffer; 2],
+    /// the live particles packed by `simulate`, it's the instance buffer of the render pipeline.
+    draw: wgpu::Buffer,
+}
+
+impl ParticleBuffers {
+    fn capacity(&self) -> u32 {
+        (self.particles.size() / size_of::<Particle>() as u64) as u32
+    }
+
+    /// The lists hold the indices of the `dead` and `alive` particles, the second alive list
+    /// starts empty.
+    fn new(
+        device: &wgpu::Device,
+        name: &str,
+        particles: &[Particle],
+        dead: &[u32],
+        alive: &[u32],
+        ping_pong: bool,
+    ) -> Self {
+        // a list is a `count` followed by `capacity` indices.
+        let list = |label: &str, indices: &[u32]| {
+            let mut contents = vec![0u32; particles.len() + 1];
+            contents[0] = indices.len() as u32;
+            contents[1..=indices.len()].copy_from_slice(indices);
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{name} {label}")),
+                contents: bytemuck::cast_slice(&contents),
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+            })
+        };
+
+        // both copies start equal, the dead particles are only written when they die.
+        let particle_buffer = |label: &str| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{name} {label}")),
+                contents: bytemuck::cast_slice(particles),
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+            })
+        };
+
+        Self {
+            particles: particle_buffer("Particle Buffer"),
+            ping_pong: ping_pong.then(|| particle_buffer("Particle Buffer B")),
+            dead_list: list("Dead List Buffer", dead),
+            alive_lists: [
+                list("Alive List Buffer A", alive),
+                list("Alive List Buffer B", &[]),
+            ],
+            draw: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(&format!("{name} Draw Buffer")),
+                size: size_of_val(particles) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    /// The compute bind groups of the emitter, the alive lists are swapped between the two.
+    #[allow(clippy::too_many_arguments)]
+    fn bind_groups(
+        &self,
+        device: &wgpu::Device,
+        name: &str,
+        scene: &SceneBindings,
+        indirect_buffer: &wgpu::Buffer,
+        depth_collision: bool,
+        grid: Option<&Grid>,
+        fluid: Option<&Fluid>,
+    ) -> [wgpu::BindGroup; 2] {
+        let compute_layout = create_compute_bind_group_layout(
+            device,
+            self.ping_pong.is_some(),
+            depth_collision,
+            grid.is_some(),
+            fluid.is_some(),
+        );
+        [0, 1].map(|i| {
+            // in ping-pong the bind group `i` reads the particles written by the other one.
+            let (src, dst) = match &self.ping_pong {
+                Some(other) => {
+                    let buffers = [&self.particles, other];
+                    (Some(buffers[i]), buffers[1 - i])
+                }
+                None => (None, &self.particles),
+            };
+            let mut entries = vec![
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: dst.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: scene.simulation.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.dead_list.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: self.alive_lists[i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: self.alive_lists[1 - i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: indirect_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: self.draw.as_entire_binding(),
+                },
+            ];
+            if let Some(src) = src {
+                entries.push(wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: src.as_entire_binding(),
+                });
+            }
+            if depth_collision {
+                entries.push(wgpu::BindGroupEntry {
+                    binding: 9,
+                    resource: scene.camera.as_entire_binding(),
+                });
+                entries.push(wgpu::BindGroupEntry {
+                    binding: 10,
+                    resource: wgpu::BindingResource::TextureView(scene.depth),
+                });
+            }
+            if let Some(grid) = grid {
+                entries.extend(grid.entries());
+            }
+            if let Some(fluid) = fluid {
+                entries.extend(fluid.entries());
+            }
+
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &compute_layout,
+                entries: &entries,
+                label: Some(&format!("{name} Compute Bind Group {i}")),
+            })
+        })
+    }
+
+    /// Copies the particles to the second buffer in ping-pong, after they were written on the gpu.
+    fn copy_to_ping_pong(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(other) = &self.ping_pong {
+            let mut encoder = device.create_command_encoder(&Default::default());
+            encoder.copy_buffer_to_buffer(&self.particles, 0, other, 0, self.particles.size());
+            queue.submit(Some(encoder.finish()));
+        }
+    }
+
+    /// Reads back the particles, the indices of the live ones from the alive list `current`
+    /// and the indices of the dead ones, it waits for the gpu.
+    fn read(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        current: usize,
+    ) -> (Vec<Particle>, Vec<u32>, Vec<u32>) {
+        // the bind group `current` reads the particles of the last step.
+        let particles = match &self.ping_pong {
+            Some(other) if current == 1 => other,
+            _ => &self.particles,
+        };
+        let sources = [particles, &self.alive_lists[current], &self.dead_list];
+        let mut encoder = device.create_command_encoder(&Default::default());
+        let staging = sources.map(|source| {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Particle Readback Buffer"),
+                size: source.size(),
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            encoder.copy_buffer_to_buffer(source, 0, &buffer, 0, source.size());
+            buffer
+        });
+        queue.submit(Some(encoder.finish()));
+        for buffer in &staging {
+            buffer.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+        }
+        device.poll(wgpu::MaintainBase::wait()).panic_on_timeout();
+
+        let particles =
+            bytemuck::cast_slice::<u8, Particle>(&staging[0].slice(..).get_mapped_range()).to_vec();
+        let list = |buffer: &wgpu::Buffer| {
+            let data = buffer.slice(..).get_mapped_range();
+            let list = bytemuck::cast_slice::<u8, u32>(&data);
+            let count = (list[0] as usize).min(particles.len());
+            list[1..=count].to_vec()
+        };
+        let (alive, dead) = (list(&staging[1]), list(&staging[2]));
+        (particles, alive, dead)
+    }
+}
+
+/// Threads of the `emit` and `simulate` workgroups, `WORKGROUP_SIZE` in wgsl.
+const WORKGROUP_SIZE: u32 = 64;
+/// Dispatches of more workgroups continue on y, `MAX_WORKGROUPS` in wgsl. It's the
+/// `max_compute_workgroups_per_dimension` guaranteed by wgpu.
+const MAX_WORKGROUPS: u32 = 65535;
+
+/// The workgroups needed by `count` threads, same as `dispatch_size` in wgsl.
+fn dispatch_size(count: u32) -> (u32, u32) {
+    let workgroups = count.div_ceil(WORKGROUP_SIZE);
+    let x = workgroups.clamp(1, MAX_WORKGROUPS);
+    (x, workgroups.div_ceil(x))
+}
+
+/// The most particles an emitter can have on a device with these `limits`, the particle and draw
+/// buffers hold all of them.
+pub fn max_capacity(limits: &wgpu::Limits) -> u32 {
+    let size = size_of::<Particle>() as u64;
+    let binding = limits.max_storage_buffer_binding_size as u64 / size;
+    let buffer = limits.max_buffer_size / size;
+    binding.min(buffer).min(MAX_CAPACITY as u64) as u32
+}
+
+/// byte offset of the `DrawIndirectArgs` inside the indirect buffer.
+const DRAW_ARGS_OFFSET: wgpu::BufferAddress = 3 * 4;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RenderUniform {
+    pub particle_size: f32,
+    pub alpha: f32,
+    _pad: [f32; 2],
+}
+
+impl Default for RenderUniform {
+    fn default() -> Self {
+        Self {
+            particle_size: 0.1,
+            alpha: 1.0,
+            _pad: [0.0; 2],
+        }
+    }
+}
+
+fn dv(rng: &mut impl Rng) -> Vector3<f32> {
+    let theta = rng.gen_range(0.0..2.0 * f32::consts::PI);
+    let phi = rng.gen_range(0.0..f32::consts::PI);
+
+    let x = phi.sin() * theta.cos();
+    let y = phi.sin() * theta.sin();
+    let z = phi.cos();
+
+    cgmath::Vector3::new(x, y, z).normalize()
+}
+
+fn gen_range(rng: &mut impl Rng, (min, max): (f32, f32)) -> f32 {
+    if min < max {
+        rng.gen_range(min..max)
+    } else {
+        min
+    }
+}
+
+/// Same as `pack4x8unorm` in wgsl.
+fn pack_color(color: [f32; 4]) -> u32 {
+    u32::from_le_bytes(color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
+
+/// The image particles are sampled from and how.
+struct SpawnImage {
+    img: DynamicImage,
+    options: ImageOptions,
+    /// the particles are filled by [`load_image_gpu`] instead of [`image_seeds`].
+    gpu: bool,
+}
+
+/// The image of a `SpawnShape::Image`, or the rasterized text of a `SpawnShape::Text`. `field`
+/// is the path of `shape` in the emitter description, for the errors.
+fn spawn_image(
+    shape: &SpawnShape,
+    budget: u32,
+    field: &str,
+) -> Result<Option<SpawnImage>, FieldError> {
+    let image = match shape {
+        SpawnShape::Image {
+            path,
+            scale,
+            sampling,
+            alpha_threshold,
+            depth,
+            gpu,
+        } => {
+            let data = match path {
+                Some(path) => ImageData::Path(path),
+                None => ImageData::Bytes(include_bytes!("../hemis.jpg")),
+            };
+            Some(SpawnImage {
+                img: decode_image(data).map_err(|e| FieldError {
+                    field: format!("{field}.path"),
+                    message: e.to_string(),
+                })?,
+                options: ImageOptions {
+                    budget,
+                    sampling: *sampling,
+                    alpha_threshold: *alpha_threshold,
+                    depth: *depth,
+                    scale: *scale,
+                },
+                gpu: *gpu,
+            })
+        }
+        SpawnShape::Text {
+            text,
+            font,
+            pixel_height,
+            scale,
+            color,
+            sampling,
+            alpha_threshold,
+        } => Some(SpawnImage {
+            img: rasterize_text(text, font.as_deref(), *pixel_height, *color).map_err(|e| {
+                FieldError {
+                    field: format!("{field}.font"),
+                    message: format!("{e:#}"),
+                }
+            })?,
+            options: ImageOptions {
+                budget,
+                sampling: *sampling,
+                alpha_threshold: *alpha_threshold,
+                depth: 0.0,
+                scale: *scale,
+            },
+            gpu: false,
+        }),
+        _ => None,
+    };
+    Ok(image)
+}
+
+/// Points of `shape`, the image, text and point cloud shapes may produce fewer than `capacity`.
+/// The seeds of an image loaded on the gpu are placeholders, one per cell of its grid.
+fn spawn_seeds(
+    shape: &SpawnShape,
+    capacity: u32,
+    image: Option<&SpawnImage>,
+    rng: &mut Pcg,
+    field: &str,
+) -> Result<Vec<Seed>, FieldError> {
+    if let Some(SpawnImage { img, options, gpu }) = image {
+        return Ok(match gpu {
+            true => vec![Seed::default(); ImageGrid::new(img.dimensions(), options).count()],
+            false => image_seeds(img, options),
+        });
+    }
+    let invalid_path = |e: anyhow::Error| FieldError {
+        field: format!("{field}.path"),
+        message: format!("{e:#}"),
+    };
+
+    let mut generate = |f: &mut dyn FnMut(&mut Pcg) -> [f32; 3]| {
+        (0..capacity)
+            .map(|_| Seed {
+                origin: f(rng),
+                color: [1.0; 4],
+            })
+            .collect()
+    };
+
+    let seeds = match shape {
+        SpawnShape::Sphere { radius } => generate(&mut |rng| (dv(rng) * *radius).into()),
+        SpawnShape::Cube { size } => {
+            let h = size / 2.0;
+            generate(&mut |rng| {
+                [
+                    gen_range(rng, (-h, h)),
+                    gen_range(rng, (-h, h)),
+                    gen_range(rng, (-h, h)),
+                ]
+            })
+        }
+        SpawnShape::PointCloud { path, scale } => {
+            load_point_cloud(path, capacity, *scale).map_err(invalid_path)?
+        }
+        SpawnShape::Mesh {
+            path,
+            scale,
+            texture,
+        } => sample_mesh(path, texture.as_deref(), capacity, *scale, rng).map_err(invalid_path)?,
+        SpawnShape::Point | SpawnShape::Image { .. } | SpawnShape::Text { .. } => {
+            generate(&mut |_| [0.0; 3])
+        }
+    };
+    Ok(seeds)
+}
+
+/// The particle buffer of the emitter, its length decides the capacity.
+fn generate_particles(
+    desc: &EmitterDesc,
+    image: Option<&SpawnImage>,
+    seed: u32,
+) -> Result<Vec<Particle>, FieldError> {
+    let mut rng = Pcg::new(0, 0, seed);
+    let seeds = spawn_seeds(&desc.spawn, desc.capacity, image, &mut rng, "spawn")?;
+
+    // the target shape is always sampled on the cpu, particles share its points when it has fewer.
+    let targets = match &desc.target {
+        Some(shape) => {
+            let budget = seeds.len() as u32;
+            let image = spawn_image(shape, budget, "target")?.map(|image| SpawnImage {
+                gpu: false,
+                ..image
+            });
+            spawn_seeds(shape, budget, image.as_ref(), &mut rng, "target")?
+        }
+        None => vec![],
+    };
+
+    let count = seeds.len();
+    let particles = seeds
+        .into_iter()
+        .enumerate()
+        .map(|(i, Seed { origin, color })| {
+            let color = desc.initial.color.unwrap_or(color);
+            let target = match targets.len() {
+                0 => Seed { origin, color },
+                // in u64, `i * len` overflows the usize of wasm32 past 65536 particles.
+                len => targets[(i as u64 * len as u64 / count as u64) as usize],
+            };
+            let target_color = desc.initial.color.unwrap_or(target.color);
+            let [x, y, z] = origin;
+
+            Particle {
+                position: [x, y, z, 0.0],
+                color,
+                dir: [(); 3].map(|_| gen_range(&mut rng, desc.initial.direction)),
+                velocity: gen_range(&mut rng, desc.initial.velocity),
+                origin,
+                age: 0.0,
+                prev_position: origin,
+                // a lifetime of 0 makes the particle start in the dead list
+                lifetime: match (&desc.emission, desc.initial.lifetime) {
+                    (Some(_), _) => 0.0,
+                    (None, Some(lifetime)) => gen_range(&mut rng, lifetime),
+                    (None, None) => -1.0,
+                },
+                morph_target: target.origin,
+                origin_color: pack_color(color),
+                target_color: pack_color(target_color),
+                morph_delay: rng.next_f32(),
+                ..Default::default()
+            }
+        })
+        .collect();
+    Ok(particles)
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu::naga;
+
+    use super::*;
+
+    #[test]
+    fn depth_collision_compiles_to_glsl() {
+        let mut builder = ShaderBuilder::new(include_str!("../shaders/vfx_compute.wgsl"));
+        builder.define("DEPTH_COLLISION");
+        let module = builder.build().unwrap();
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+
+        for entry_point in &module.entry_points {
+            let mut glsl = String::new();
+            let options = naga::back::glsl::Options {
+                version: naga::back::glsl::Version::Embedded {
+                    version: 310,
+                    is_webgl: false,
+                },
+                ..Default::default()
+            };
+            let pipeline_options = naga::back::glsl::PipelineOptions {
+                shader_stage: entry_point.stage,
+                entry_point: entry_point.name.clone(),
+                multiview: None,
+            };
+            naga::back::glsl::Writer::new(
+                &mut glsl,
+                &module,
+                &info,
+                &options,
+                &pipeline_options,
+                naga::proc::BoundsCheckPolicies::default(),
+            )
+            .and_then(|mut writer| writer.write())
+            .unwrap_or_else(|e| panic!("`{}` can't be written as glsl: {e}", entry_point.name));
+        }
+    }
+
+    #[test]
+    fn dispatch_covers_every_particle_once() {
+        let row = MAX_WORKGROUPS * WORKGROUP_SIZE;
+        for count in [1, WORKGROUP_SIZE + 1, row, row + 1, 2 * row + 1000] {
+            let (x, y) = dispatch_size(count);
+            assert!((1..=MAX_WORKGROUPS).contains(&x) && (1..=MAX_WORKGROUPS).contains(&y));
+
+            // `thread_index` of every thread of the dispatch.
+            let mut threads = vec![0u8; count as usize];
+            for global_y in 0..y {
+                for global_x in 0..x * WORKGROUP_SIZE {
+                    let index = global_x + global_y * x * WORKGROUP_SIZE;
+                    if index < count {
+                        threads[index as usize] += 1;
+                    }
+                }
+            }
+            assert!(threads.iter().all(|&n| n == 1), "{count} particles");
+        }
+        // no particle, no workgroup.
+        assert_eq!(dispatch_size(0), (1, 0));
+    }
+}
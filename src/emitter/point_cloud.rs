@@ -0,0 +1,327 @@
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use super::Seed;
+
+/// Loads the points of a `.ply` or `.xyz` file, the format is picked from the extension. Points
+/// without a color are white, clouds with more points than `budget` keep evenly spaced ones.
+pub fn load_point_cloud(path: &Path, budget: u32, scale: f32) -> Result<Vec<Seed>> {
+    let file = std::fs::File::open(path).with_context(|| path.display().to_string())?;
+    let mut reader = BufReader::new(file);
+    let seeds = match extension(path).as_deref() {
+        Some("ply") => read_ply(&mut reader),
+        Some("xyz") => read_xyz(reader),
+        _ => bail!("{}: expected a .ply or .xyz file", path.display()),
+    }
+    .with_context(|| path.display().to_string())?;
+
+    Ok(reduce(seeds, budget)
+        .map(|seed| Seed {
+            origin: seed.origin.map(|c| c * scale),
+            ..seed
+        })
+        .collect())
+}
+
+pub(super) fn extension(path: &Path) -> Option<String> {
+    Some(path.extension()?.to_str()?.to_ascii_lowercase())
+}
+
+/// At most `budget` seeds evenly spaced over the whole cloud.
+fn reduce(seeds: Vec<Seed>, budget: u32) -> impl Iterator<Item = Seed> {
+    let len = seeds.len();
+    let count = len.min(budget as usize);
+    (0..count).map(move |i| seeds[i * len / count])
+}
+
+/// Plain text, one `x y z` point per line optionally followed by `r g b`. Colors are read as
+/// 0-255 when any of them is above 1, lines starting with `#` are skipped.
+fn read_xyz(reader: impl BufRead) -> Result<Vec<Seed>> {
+    let mut points = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let values = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|v| !v.is_empty())
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("line {}", i + 1))?;
+        points.push(match values.as_slice() {
+            [x, y, z] => ([*x, *y, *z], None),
+            [x, y, z, r, g, b, ..] => ([*x, *y, *z], Some([*r, *g, *b])),
+            _ => bail!("line {}: expected `x y z` or `x y z r g b`", i + 1),
+        });
+    }
+
+    let bytes = points
+        .iter()
+        .flat_map(|(_, color)| color.iter().flatten())
+        .any(|c| *c > 1.0);
+    let max = if bytes { 255.0 } else { 1.0 };
+    Ok(points
+        .into_iter()
+        .map(|(origin, color)| {
+            let [r, g, b] = color.map_or([1.0; 3], |color| color.map(|c| c / max));
+            Seed {
+                origin,
+                color: [r, g, b, 1.0],
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => bail!("unknown property type `{name}`"),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// colors stored as integers go from 0 to their max value.
+    fn normalize(self, value: f64) -> f32 {
+        (match self {
+            Self::U8 => value / u8::MAX as f64,
+            Self::U16 => value / u16::MAX as f64,
+            _ => value,
+        }) as f32
+    }
+}
+
+#[derive(Debug)]
+struct PlyProperty {
+    name: String,
+    ty: PlyType,
+    /// type of the length of a list property.
+    list: Option<PlyType>,
+}
+
+#[derive(Debug)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+/// Reads the `vertex` element of an ascii or binary ply file, its `x`, `y`, `z` and its
+/// `red`, `green`, `blue`, `alpha` properties when present. The other elements are skipped.
+fn read_ply(reader: &mut impl BufRead) -> Result<Vec<Seed>> {
+    let (format, elements) = read_ply_header(reader)?;
+    let mut values = PlyValues::new(format, reader);
+
+    for element in elements {
+        if element.name != "vertex" {
+            for _ in 0..element.count {
+                for property in &element.properties {
+                    values.property(property)?;
+                }
+            }
+            continue;
+        }
+
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|p| names.contains(&p.name.as_str()))
+        };
+        let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let [Some(x), Some(y), Some(z)] = position else {
+            bail!("the vertex element has no x, y and z properties");
+        };
+        let color = [
+            find(&["red", "r", "diffuse_red"]),
+            find(&["green", "g", "diffuse_green"]),
+            find(&["blue", "b", "diffuse_blue"]),
+            find(&["alpha", "a"]),
+        ];
+
+        let mut seeds = Vec::with_capacity(element.count);
+        let mut row = vec![0.0; element.properties.len()];
+        for _ in 0..element.count {
+            for (value, property) in row.iter_mut().zip(&element.properties) {
+                *value = values.property(property)?;
+            }
+            let channel = |i: Option<usize>| match i {
+                Some(i) => element.properties[i].ty.normalize(row[i]),
+                None => 1.0,
+            };
+            seeds.push(Seed {
+                origin: [row[x] as f32, row[y] as f32, row[z] as f32],
+                color: color.map(channel),
+            });
+        }
+        return Ok(seeds);
+    }
+    bail!("no vertex element")
+}
+
+fn read_ply_header(reader: &mut impl BufRead) -> Result<(PlyFormat, Vec<PlyElement>)> {
+    let mut line = String::new();
+    let mut next_line = |line: &mut String| -> Result<()> {
+        line.clear();
+        if reader.read_line(line)? == 0 {
+            bail!("unexpected end of the header");
+        }
+        Ok(())
+    };
+
+    next_line(&mut line)?;
+    if line.trim() != "ply" {
+        bail!("missing the `ply` magic number");
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = vec![];
+    loop {
+        next_line(&mut line)?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["end_header"] => break,
+            ["format", name, _] => {
+                format = Some(match *name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::LittleEndian,
+                    "binary_big_endian" => PlyFormat::BigEndian,
+                    _ => bail!("unknown format `{name}`"),
+                })
+            }
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count.parse()?,
+                properties: vec![],
+            }),
+            ["property", "list", len, ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| anyhow!("property `{name}` outside of an element"))?
+                .properties
+                .push(PlyProperty {
+                    name: name.to_string(),
+                    ty: PlyType::parse(ty)?,
+                    list: Some(PlyType::parse(len)?),
+                }),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| anyhow!("property `{name}` outside of an element"))?
+                .properties
+                .push(PlyProperty {
+                    name: name.to_string(),
+                    ty: PlyType::parse(ty)?,
+                    list: None,
+                }),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => bail!("unexpected header line `{}`", line.trim()),
+        }
+    }
+
+    Ok((format.ok_or_else(|| anyhow!("missing format"))?, elements))
+}
+
+/// Reads the values of the body of a ply file one at a time, whatever its format.
+struct PlyValues<'a, R> {
+    format: PlyFormat,
+    reader: &'a mut R,
+    /// the words of the current ascii line that were not read yet, reversed.
+    words: Vec<String>,
+}
+
+impl<'a, R: BufRead> PlyValues<'a, R> {
+    fn new(format: PlyFormat, reader: &'a mut R) -> Self {
+        Self {
+            format,
+            reader,
+            words: vec![],
+        }
+    }
+
+    /// The value of a scalar property, a list is skipped and reads as 0.
+    fn property(&mut self, property: &PlyProperty) -> Result<f64> {
+        match property.list {
+            Some(len) => {
+                let len = self.value(len)? as usize;
+                for _ in 0..len {
+                    self.value(property.ty)?;
+                }
+                Ok(0.0)
+            }
+            None => self.value(property.ty),
+        }
+    }
+
+    fn value(&mut self, ty: PlyType) -> Result<f64> {
+        if self.format == PlyFormat::Ascii {
+            while self.words.is_empty() {
+                let mut line = String::new();
+                if self.reader.read_line(&mut line)? == 0 {
+                    bail!("unexpected end of file");
+                }
+                self.words = line.split_whitespace().rev().map(String::from).collect();
+            }
+            let word = self.words.pop().unwrap();
+            return word
+                .parse()
+                .with_context(|| format!("invalid value `{word}`"));
+        }
+
+        let mut bytes = [0u8; 8];
+        let bytes = &mut bytes[..ty.size()];
+        self.reader
+            .read_exact(bytes)
+            .context("unexpected end of file")?;
+        if self.format == PlyFormat::BigEndian {
+            bytes.reverse();
+        }
+        Ok(match ty {
+            PlyType::I8 => bytes[0] as i8 as f64,
+            PlyType::U8 => bytes[0] as f64,
+            PlyType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            PlyType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            PlyType::I32 => i32::from_le_bytes(bytes.try_into()?) as f64,
+            PlyType::U32 => u32::from_le_bytes(bytes.try_into()?) as f64,
+            PlyType::F32 => f32::from_le_bytes(bytes.try_into()?) as f64,
+            PlyType::F64 => f64::from_le_bytes(bytes.try_into()?),
+        })
+    }
+}
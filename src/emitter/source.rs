@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+use serde::Deserialize;
+
+/// Where a particle is created and its color, the loaders of this module produce one per particle.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Seed {
+    pub origin: [f32; 3],
+    pub color: [f32; 4],
+}
+
+pub enum ImageData<'a> {
+    Path(&'a Path),
+    Bytes(&'a [u8]),
+}
+
+/// How an image larger than the particle budget is reduced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Sampling {
+    /// scales the image down, every particle averages the pixels it covers.
+    #[default]
+    Resample,
+    /// keeps one pixel out of `n` in both directions.
+    Stride,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    /// particles produced at most.
+    pub budget: u32,
+    pub sampling: Sampling,
+    /// pixels with a lower alpha are dropped.
+    pub alpha_threshold: f32,
+    /// the luminance of a pixel pushes it along z by up to `depth` units.
+    pub depth: f32,
+    /// half the size of the longest side of the image, in world units.
+    pub scale: f32,
+}
+
+/// The pixels of an image kept within the particle budget: `width` by `height` particles, one
+/// every `stride` pixels of the image, or one per pixel of the image resized to the grid when
+/// `stride` is 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageGrid {
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+}
+
+impl ImageGrid {
+    pub fn new((width, height): (u32, u32), options: &ImageOptions) -> Self {
+        let budget = options.budget.max(1) as u64;
+        let pixels = width as u64 * height as u64;
+
+        match options.sampling {
+            _ if pixels <= budget => Self {
+                width,
+                height,
+                stride: 1,
+            },
+            Sampling::Resample => {
+                let factor = (budget as f64 / pixels as f64).sqrt();
+                Self {
+                    width: ((width as f64 * factor) as u32).max(1),
+                    height: ((height as f64 * factor) as u32).max(1),
+                    stride: 0,
+                }
+            }
+            Sampling::Stride => {
+                let mut stride = (pixels as f64 / budget as f64).sqrt().ceil() as u32;
+                while width.div_ceil(stride) as u64 * height.div_ceil(stride) as u64 > budget {
+                    stride += 1;
+                }
+                Self {
+                    width: width.div_ceil(stride),
+                    height: height.div_ceil(stride),
+                    stride,
+                }
+            }
+        }
+    }
+
+    /// particles produced before the alpha threshold is applied.
+    pub fn count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+}
+
+/// Decodes an image from a file, guessing the format from its extension, or from memory.
+pub fn decode_image(data: ImageData) -> Result<DynamicImage, image::ImageError> {
+    match data {
+        ImageData::Path(path) => image::open(path),
+        ImageData::Bytes(bytes) => image::load_from_memory(bytes),
+    }
+}
+
+/// One [`Seed`] per kept pixel of the image, never more than `options.budget`.
+pub fn image_seeds(img: &DynamicImage, options: &ImageOptions) -> Vec<Seed> {
+    let grid = ImageGrid::new(img.dimensions(), options);
+    let (img, stride) = match grid.stride {
+        0 => (
+            img.resize_exact(grid.width, grid.height, FilterType::Triangle),
+            1,
+        ),
+        stride => (img.clone(), stride),
+    };
+    let img = img.to_rgba8();
+    let (width, height) = img.dimensions();
+    // the longest side goes from -scale to scale, the other one keeps the aspect ratio.
+    let extent = width.max(height) as f32 / 2.0;
+
+    let mut seeds = vec![];
+    for y in (0..height).step_by(stride as usize) {
+        for x in (0..width).step_by(stride as usize) {
+            let pixel = img.get_pixel(x, y);
+            let [r, g, b, a] = pixel.0.map(|c| c as f32 / 255.0);
+            if a < options.alpha_threshold {
+                continue;
+            }
+
+            let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            let world_x = (x as f32 + 0.5 - width as f32 / 2.0) / extent;
+            let world_y = (y as f32 + 0.5 - height as f32 / 2.0) / extent;
+            // the rows of the image go down, the y axis goes up.
+            seeds.push(Seed {
+                origin: [
+                    world_x * options.scale,
+                    -world_y * options.scale,
+                    luminance * options.depth,
+                ],
+                color: [r, g, b, a],
+            });
+        }
+    }
+    seeds
+}
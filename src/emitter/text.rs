@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use anyhow::{Context, Result};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+/// Rasterizes `text` with a `.ttf` or `.otf` font, or the default egui font when `font` is not
+/// set, into an image whose alpha is the coverage of the glyphs. The lines are centered and
+/// `pixel_height` is the height of a line in pixels.
+///
+/// The image goes through [`image_seeds`](super::image_seeds) like any other image.
+pub fn rasterize_text(
+    text: &str,
+    font: Option<&Path>,
+    pixel_height: f32,
+    color: [f32; 4],
+) -> Result<DynamicImage> {
+    let font = match font {
+        Some(path) => {
+            let bytes = std::fs::read(path).with_context(|| path.display().to_string())?;
+            FontVec::try_from_vec(bytes).with_context(|| path.display().to_string())?
+        }
+        None => {
+            let fonts = egui::FontDefinitions::default();
+            FontVec::try_from_vec(fonts.font_data["Ubuntu-Light"].font.to_vec())?
+        }
+    };
+    let font = font.as_scaled(PxScale::from(pixel_height));
+
+    let lines = text
+        .lines()
+        .map(|line| {
+            let mut caret = 0.0;
+            let mut previous = None;
+            let glyphs = line
+                .chars()
+                .map(|c| {
+                    let id = font.glyph_id(c);
+                    if let Some(previous) = previous {
+                        caret += font.kern(previous, id);
+                    }
+                    previous = Some(id);
+                    let glyph = id.with_scale(font.scale());
+                    let x = caret;
+                    caret += font.h_advance(id);
+                    (glyph, x)
+                })
+                .collect::<Vec<_>>();
+            (glyphs, caret)
+        })
+        .collect::<Vec<_>>();
+
+    let line_height = font.height() + font.line_gap();
+    let width = lines.iter().map(|(_, width)| *width).fold(0.0, f32::max);
+    let height = line_height * lines.len() as f32;
+    // the empty pixels have the color of the text too, so resampling does not darken the edges.
+    let [r, g, b, a] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    let mut img = RgbaImage::from_pixel(
+        width.ceil().max(1.0) as u32,
+        height.ceil().max(1.0) as u32,
+        Rgba([r, g, b, 0]),
+    );
+
+    for (row, (glyphs, line_width)) in lines.into_iter().enumerate() {
+        let offset = (width - line_width) / 2.0;
+        let baseline = line_height * row as f32 + font.ascent();
+        for (mut glyph, x) in glyphs {
+            glyph.position = ab_glyph::point(offset + x, baseline);
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|x, y, coverage| {
+                let x = bounds.min.x as i32 + x as i32;
+                let y = bounds.min.y as i32 + y as i32;
+                if x < 0 || y < 0 || x >= img.width() as i32 || y >= img.height() as i32 {
+                    return;
+                }
+                let pixel = img.get_pixel_mut(x as u32, y as u32);
+                // overlapping glyphs keep the strongest coverage.
+                let alpha = (coverage.clamp(0.0, 1.0) * a as f32).round() as u8;
+                *pixel = Rgba([r, g, b, pixel.0[3].max(alpha)]);
+            });
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(img))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emitter::source::{image_seeds, ImageOptions, Sampling};
+
+    #[test]
+    fn first_glyph_is_on_the_left() {
+        // the spaces widen the image to the right of the only drawn glyph.
+        let img = rasterize_text("I    ", None, 32.0, [1.0; 4]).unwrap();
+        let options = ImageOptions {
+            budget: u32::MAX,
+            sampling: Sampling::Stride,
+            alpha_threshold: 0.5,
+            depth: 0.0,
+            scale: 1.0,
+        };
+        let seeds = image_seeds(&img, &options);
+        assert!(!seeds.is_empty());
+        assert!(seeds.iter().all(|seed| seed.origin[0] < 0.0));
+    }
+}
@@ -7,9 +7,11 @@ use wgpu::core::device;
 use wgpu::{SurfaceTexture, TextureFormat};
 use winit::{dpi::PhysicalSize, event::*, window::Window};
 
+use crate::attr::vec3_ui;
 use crate::egui::EguiRenderer;
+use crate::emitter::{max_capacity, SceneDesc};
 use crate::profiler::{self, Profiler, QueryTimestampPass};
-use crate::system::System;
+use crate::system::{required_limits, System, MAX_TIME_SCALE, MIN_TIME_SCALE};
 use crate::window::InputEvent;
 
 #[cfg(target_arch = "wasm32")]
@@ -31,7 +33,7 @@ pub struct GpuState {
 }
 
 impl GpuState {
-    pub async fn new(window: Window, size: PhysicalSize<u32>) -> Self {
+    pub async fn new(window: Window, size: PhysicalSize<u32>, desc: SceneDesc) -> Self {
         let window = Rc::new(window);
 
         #[cfg(not(target_arch = "wasm32"))]
@@ -72,7 +74,7 @@ impl GpuState {
                         | wgpu::Features::TIMESTAMP_QUERY
                         | wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS
                         | wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES,
-                    required_limits: wgpu::Limits::default(),
+                    required_limits: required_limits(&adapter),
                 },
                 None,
             )
@@ -106,7 +108,14 @@ impl GpuState {
         };
         surface.configure(&device, &config);
 
-        let system = System::new(&device, &config);
+        let size = (config.width, config.height);
+        // a scene the device can't build is reported and replaced by the builtin one.
+        let system = System::new(&device, &queue, config.format, size, &desc)
+            .or_else(|e| {
+                report_error(e);
+                System::new(&device, &queue, config.format, size, &SceneDesc::builtin())
+            })
+            .expect("The builtin scene can't be built on this device");
         let egui = EguiRenderer::new(&device, config.format, None, 1, window.as_ref());
 
         let mut profiler = Profiler::default();
@@ -174,6 +183,10 @@ impl GpuState {
             query.resolve(&mut encoder);
         }
 
+        // a new capacity is applied once the frame that still uses the old buffers is submitted.
+        let mut resize = None;
+        // the live particles are only read back while the DEBUG window shows them.
+        let mut stats_open = false;
         {
             self.egui.begin_frame(&self.window);
 
@@ -184,6 +197,7 @@ impl GpuState {
                 .anchor(Align2::RIGHT_TOP, [0.0, 0.0])
                 .default_size([200.0, 75.0])
                 .show(self.egui.context(), |ui| {
+                    stats_open = true;
                     ui.label(format!("FPS: {}", (1.0 / dt.as_secs_f64())));
                     //TODO
                     ui.label(format!(
@@ -195,10 +209,72 @@ impl GpuState {
                         self.profiler.timestamps.get(1).unwrap().pass_time
                     ));
 
-                    let _ = ui.add(egui::Slider::new(
-                        &mut self.system.particle_uniform.data.velocity.vel,
-                        -100.0..=100.0,
-                    ));
+                    // Space pauses, '.' steps and '-'/'=' change the time scale as well.
+                    ui.horizontal(|ui| {
+                        let paused = self.system.paused();
+                        if ui.button(if paused { "Resume" } else { "Pause" }).clicked() {
+                            self.system.set_paused(!paused);
+                        }
+                        if ui.button("Step").clicked() {
+                            self.system.step_once();
+                        }
+                    });
+                    let mut time_scale = self.system.time_scale();
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut time_scale, MIN_TIME_SCALE..=MAX_TIME_SCALE)
+                                .logarithmic(true)
+                                .text("time scale"),
+                        )
+                        .changed()
+                    {
+                        self.system.set_time_scale(time_scale);
+                    }
+                    egui::CollapsingHeader::new("simulation").show(ui, |ui| {
+                        vec3_ui(ui, "gravity", &mut self.system.simulation.gravity);
+                        vec3_ui(ui, "wind", &mut self.system.simulation.wind);
+                    });
+
+                    let max_capacity = max_capacity(&self.device.limits());
+                    for (i, emitter) in self.system.emitters.iter_mut().enumerate() {
+                        egui::CollapsingHeader::new(&emitter.name)
+                            .id_salt(i)
+                            .show(ui, |ui| {
+                                ui.label(format!(
+                                    "Alive: {} / {}",
+                                    emitter.alive_count,
+                                    emitter.capacity()
+                                ));
+                                // the buffers are reallocated when the slider is released.
+                                let id = ui.make_persistent_id("capacity");
+                                let mut capacity = ui
+                                    .data(|data| data.get_temp(id))
+                                    .unwrap_or(emitter.capacity());
+                                let response = ui.add(
+                                    egui::Slider::new(&mut capacity, 1..=max_capacity)
+                                        .logarithmic(true)
+                                        .text("capacity"),
+                                );
+                                if response.dragged() {
+                                    ui.data_mut(|data| data.insert_temp(id, capacity));
+                                } else {
+                                    ui.data_mut(|data| data.remove::<u32>(id));
+                                    if capacity != emitter.capacity()
+                                        && (response.changed() || response.drag_stopped())
+                                    {
+                                        resize = Some((i, capacity));
+                                    }
+                                }
+                                emitter.attributes.ui(ui);
+                                emitter.colliders.data.ui(ui);
+                                if let Some(grid) = &mut emitter.grid {
+                                    grid.ui(ui, emitter.attributes.reach());
+                                }
+                                if let Some(fluid) = &mut emitter.fluid {
+                                    fluid.ui(ui);
+                                }
+                            });
+                    }
                 });
 
             let screen_descriptor = ScreenDescriptor {
@@ -218,6 +294,22 @@ impl GpuState {
         self.queue.submit(Some(encoder.finish()));
         frame.present();
 
+        if stats_open {
+            self.system.read_stats(&self.device, false);
+        }
+        if let Some((emitter, capacity)) = resize {
+            match self
+                .system
+                .set_capacity(&self.device, &self.queue, emitter, capacity)
+            {
+                Ok(applied) if applied < capacity => report_error(format!(
+                    "emitters[{emitter}].capacity: the spawn shape only has {applied} particles"
+                )),
+                Ok(_) => {}
+                Err(e) => report_error(e),
+            }
+        }
+
         for query in &mut self.profiler.timestamps {
             query.map();
 
@@ -229,3 +321,14 @@ impl GpuState {
         }
     }
 }
+
+/// Reports an error of the scene, there's no window to show it in and only the web has a logger.
+fn report_error(error: impl std::fmt::Display) {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            log::error!("{error}");
+        } else {
+            eprintln!("{error}");
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::*;
+use image::RgbaImage;
+
+use crate::emitter::SceneDesc;
+use crate::profiler::{Profiler, QueryTimestampPass};
+use crate::system::{required_limits, System};
+use crate::texture::Texture;
+
+const TIMESTAMP_FEATURES: wgpu::Features = wgpu::Features::TIMESTAMP_QUERY
+    .union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS)
+    .union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES);
+
+/// Options for [`run_headless`].
+pub struct HeadlessOptions {
+    pub size: (u32, u32),
+    pub frames: u32,
+    /// time step fed to [`System::update`] on every frame, it does not depend on the wall clock so
+    /// the same options always produce the same frames.
+    pub dt: instant::Duration,
+    /// frames are written as `frame_00000.png`, `frame_00001.png`... inside this directory.
+    pub output: PathBuf,
+    pub force_fallback_adapter: bool,
+    pub scene: SceneDesc,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            size: (800, 600),
+            frames: 1,
+            dt: instant::Duration::from_secs_f64(1.0 / 60.0),
+            output: PathBuf::from("frames"),
+            force_fallback_adapter: false,
+            scene: SceneDesc::builtin(),
+        }
+    }
+}
+
+/// Headless is the windowless counterpart of the GpuState: it owns a device created without a
+/// compatible surface and renders the [`System`] into an offscreen [`Texture`] that can be read
+/// back and written to disk.
+pub struct Headless {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    system: System,
+    profiler: Profiler,
+    target: Texture,
+    size: (u32, u32),
+    padded_bytes_per_row: u32,
+    readback_buffer: wgpu::Buffer,
+}
+
+impl Headless {
+    pub async fn new(
+        size: (u32, u32),
+        force_fallback_adapter: bool,
+        desc: &SceneDesc,
+    ) -> Result<Self> {
+        // WGPU_BACKEND picks the backends, otherwise hosts with only GL fall back to every backend.
+        let candidates = match wgpu::util::backend_bits_from_env() {
+            Some(backends) => vec![backends],
+            None => vec![wgpu::Backends::PRIMARY, wgpu::Backends::all()],
+        };
+        let mut adapter = None;
+        for backends in candidates {
+            let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+                backends,
+                ..Default::default()
+            });
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    force_fallback_adapter,
+                    compatible_surface: None,
+                })
+                .await;
+            if adapter.is_some() {
+                break;
+            }
+        }
+        let adapter = adapter.context("No adapter available for headless rendering")?;
+        println!("headless adapter: {:?}", adapter.get_info());
+
+        // software rasterizers do not always expose timestamps, the profiler is just left empty.
+        let timestamps = adapter.features().contains(TIMESTAMP_FEATURES);
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Headless device"),
+                    memory_hints: wgpu::MemoryHints::default(),
+                    required_features: if timestamps {
+                        TIMESTAMP_FEATURES
+                    } else {
+                        wgpu::Features::empty()
+                    },
+                    required_limits: required_limits(&adapter),
+                },
+                None,
+            )
+            .await?;
+
+        let target = Texture::empty(&device, size, Some("Headless target"))?;
+        let system = System::new(&device, &queue, target.texture.format(), size, desc)?;
+
+        let mut profiler = Profiler::default();
+        if timestamps {
+            profiler.add_query_timestamp_pass(QueryTimestampPass::new(Some("Render"), &device));
+            profiler.add_query_timestamp_pass(QueryTimestampPass::new(Some("Compute"), &device));
+        }
+
+        let padded_bytes_per_row =
+            (size.0 * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Headless readback buffer"),
+            size: (padded_bytes_per_row * size.1) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            system,
+            profiler,
+            target,
+            size,
+            padded_bytes_per_row,
+            readback_buffer,
+        })
+    }
+
+    pub fn system(&mut self) -> &mut System {
+        &mut self.system
+    }
+
+    /// Advances the simulation by `dt` and renders it into the offscreen target.
+    pub fn frame(&mut self, dt: instant::Duration) {
+        self.system.update(&self.queue, dt);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Headless encoder"),
+            });
+
+        self.system
+            .render(&mut encoder, &self.target.view, &self.profiler);
+
+        for query in &self.profiler.timestamps {
+            query.resolve(&mut encoder);
+        }
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.target.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.size.1),
+                },
+            },
+            self.target.texture.size(),
+        );
+
+        self.queue.submit(Some(encoder.finish()));
+        // the counts are printed with the frame they belong to.
+        self.system.read_stats(&self.device, true);
+
+        for query in &mut self.profiler.timestamps {
+            query.map();
+
+            self.device
+                .poll(wgpu::MaintainBase::wait())
+                .panic_on_timeout();
+
+            query.unmap();
+        }
+    }
+
+    /// Reads back the last frame rendered by [`Headless::frame`].
+    pub fn read_frame(&self) -> Result<RgbaImage> {
+        let slice = self.readback_buffer.slice(..);
+        let (tx, rx) = flume::bounded(1);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device
+            .poll(wgpu::MaintainBase::wait())
+            .panic_on_timeout();
+        rx.recv()??;
+
+        let (width, height) = self.size;
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                // the offscreen target is Bgra8UnormSrgb
+                for bgra in row[..(width * 4) as usize].chunks(4) {
+                    pixels.extend_from_slice(&[bgra[2], bgra[1], bgra[0], bgra[3]]);
+                }
+            }
+        }
+        self.readback_buffer.unmap();
+
+        RgbaImage::from_raw(width, height, pixels).context("Frame size mismatch")
+    }
+
+    pub fn save_frame(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        self.read_frame()?
+            .save(path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn render_time(&self) -> Option<f64> {
+        self.profiler
+            .timestamps
+            .first()
+            .map(|query| query.pass_time)
+    }
+
+    pub fn update_time(&self) -> Option<f64> {
+        self.profiler.timestamps.get(1).map(|query| query.pass_time)
+    }
+}
+
+/// Renders `options.frames` frames without a window and writes them as PNG files.
+pub fn run_headless(options: HeadlessOptions) -> Result<()> {
+    use pollster::FutureExt;
+
+    std::fs::create_dir_all(&options.output)?;
+
+    let mut headless =
+        Headless::new(options.size, options.force_fallback_adapter, &options.scene).block_on()?;
+
+    for frame in 0..options.frames {
+        headless.frame(options.dt);
+        headless.save_frame(options.output.join(format!("frame_{frame:05}.png")))?;
+
+        if let (Some(render), Some(update)) = (headless.render_time(), headless.update_time()) {
+            println!("frame {frame}: render {render}µs, update {update}µs");
+        }
+        for emitter in &headless.system().emitters {
+            println!(
+                "frame {frame}: {} alive {} / {}",
+                emitter.name,
+                emitter.alive_count,
+                emitter.capacity()
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -1,17 +1,28 @@
 mod attr;
 mod camera;
+pub mod collider;
 mod egui;
+mod emitter;
 mod gpu;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+pub mod particle;
+pub mod pcg;
 mod postproc;
 mod profiler;
 mod quad;
+pub mod sph;
 mod system;
 mod texture;
+mod timestep;
 mod uniform;
 mod window;
 mod window_proxy;
 
+pub use emitter::{DescError, EmitterDesc, SceneDesc};
 use gpu::GpuState;
+#[cfg(not(target_arch = "wasm32"))]
+pub use headless::{run_headless, Headless, HeadlessOptions};
 use window::App;
 use winit::event_loop::{ControlFlow, EventLoop};
 
@@ -20,11 +31,16 @@ use wasm_bindgen::prelude::*;
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub fn run() {
+    run_with(SceneDesc::builtin());
+}
+
+/// Opens the window and simulates the scene described by `desc`.
+pub fn run_with(desc: SceneDesc) {
     let event_loop = EventLoop::<GpuState>::with_user_event().build().unwrap();
 
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let mut app = App::new(&event_loop);
+    let mut app = App::new(&event_loop, desc);
 
     #[cfg(not(target_arch = "wasm32"))]
     event_loop.run_app(&mut app).unwrap();
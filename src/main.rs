@@ -1,5 +1,12 @@
-use particle_system::run;
+use particle_system::{run_with, SceneDesc};
 
-fn main() {
-    run();
+/// `particle_system [SCENE_FILE]`
+fn main() -> Result<(), particle_system::DescError> {
+    let desc = match std::env::args().nth(1) {
+        Some(path) => SceneDesc::from_file(path)?,
+        None => SceneDesc::builtin(),
+    };
+
+    run_with(desc);
+    Ok(())
 }
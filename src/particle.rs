@@ -0,0 +1,258 @@
+//! The layout of a particle, shared by the particle buffers, the `Particle` struct of the
+//! `vfx::particle` wgsl module and the instance buffer of the render pipeline.
+
+use std::mem::{offset_of, size_of};
+
+use wgpu::naga;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Particle {
+    pub position: [f32; 4],
+    pub color: [f32; 4],
+    pub dir: [f32; 3],
+    pub velocity: f32,
+    pub origin: [f32; 3],
+    pub age: f32,
+    pub prev_position: [f32; 3],
+    pub lifetime: f32,
+    pub morph_target: [f32; 3],
+    pub origin_color: u32,
+    pub target_color: u32,
+    pub morph_delay: f32,
+    // wgsl rounds the struct up to the 16 bytes alignment of its vectors.
+    pub(crate) _pad: [u32; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldType {
+    F32,
+    U32,
+    Vec3f,
+    Vec4f,
+}
+
+impl FieldType {
+    fn wgsl(self) -> &'static str {
+        match self {
+            Self::F32 => "f32",
+            Self::U32 => "u32",
+            Self::Vec3f => "vec3f",
+            Self::Vec4f => "vec4f",
+        }
+    }
+
+    fn matches(self, inner: &naga::TypeInner) -> bool {
+        use naga::{Scalar, TypeInner, VectorSize};
+        matches!(
+            (self, inner),
+            (Self::F32, TypeInner::Scalar(Scalar::F32))
+                | (Self::U32, TypeInner::Scalar(Scalar::U32))
+                | (
+                    Self::Vec3f,
+                    TypeInner::Vector {
+                        size: VectorSize::Tri,
+                        scalar: Scalar::F32,
+                    }
+                )
+                | (
+                    Self::Vec4f,
+                    TypeInner::Vector {
+                        size: VectorSize::Quad,
+                        scalar: Scalar::F32,
+                    }
+                )
+        )
+    }
+
+    fn vertex_format(self) -> wgpu::VertexFormat {
+        match self {
+            Self::F32 => wgpu::VertexFormat::Float32,
+            Self::U32 => wgpu::VertexFormat::Uint32,
+            Self::Vec3f => wgpu::VertexFormat::Float32x3,
+            Self::Vec4f => wgpu::VertexFormat::Float32x4,
+        }
+    }
+}
+
+struct Field {
+    name: &'static str,
+    ty: FieldType,
+    offset: usize,
+    /// `@location` of the field in the `In` struct of `vfx_render.wgsl`.
+    location: Option<u32>,
+    comment: &'static str,
+}
+
+macro_rules! field {
+    ($name:ident: $ty:ident, $location:expr, $comment:literal) => {
+        Field {
+            name: stringify!($name),
+            ty: FieldType::$ty,
+            offset: offset_of!(Particle, $name),
+            location: $location,
+            comment: $comment,
+        }
+    };
+}
+
+/// Every field of [`Particle`] but the padding, in order.
+const FIELDS: [Field; 12] = [
+    field!(position: Vec4f, Some(1), ""),
+    field!(color: Vec4f, Some(2), ""),
+    field!(dir: Vec3f, None, ""),
+    field!(velocity: F32, None, ""),
+    field!(origin: Vec3f, None, ""),
+    field!(age: F32, Some(4), ""),
+    field!(
+        prev_position: Vec3f,
+        Some(3),
+        "position before the last step, used to interpolate the drawn particles"
+    ),
+    field!(lifetime: F32, Some(5), "negative lifetimes never expire"),
+    field!(
+        morph_target: Vec3f,
+        None,
+        "where the `morph` module takes the particle"
+    ),
+    field!(
+        origin_color: U32,
+        None,
+        "color at the origin, packed with `pack4x8unorm`"
+    ),
+    field!(
+        target_color: U32,
+        None,
+        "color at the target, packed with `pack4x8unorm`"
+    ),
+    field!(
+        morph_delay: F32,
+        None,
+        "between 0 and 1, how late the particle starts morphing"
+    ),
+];
+
+impl Particle {
+    /// The wgsl declaration of the struct, it replaces `;;PARTICLE_STRUCT` in the
+    /// `vfx::particle` module.
+    pub fn wgsl() -> String {
+        let mut wgsl = "struct Particle {\n".to_string();
+        for field in &FIELDS {
+            wgsl += &format!("  {}: {},", field.name, field.ty.wgsl());
+            if !field.comment.is_empty() {
+                wgsl += &format!(" // {}", field.comment);
+            }
+            wgsl += "\n";
+        }
+        wgsl + "}\n"
+    }
+
+    /// The fields read by the vertex shader, the particles are the instances.
+    pub fn vertex_attributes() -> Vec<wgpu::VertexAttribute> {
+        FIELDS
+            .iter()
+            .filter_map(|field| {
+                Some(wgpu::VertexAttribute {
+                    format: field.ty.vertex_format(),
+                    offset: field.offset as wgpu::BufferAddress,
+                    shader_location: field.location?,
+                })
+            })
+            .collect()
+    }
+
+    pub fn vertex_layout(attributes: &[wgpu::VertexAttribute]) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Particle>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes,
+        }
+    }
+
+    /// Panics if the `Particle` struct of a composed `module` does not have the layout of this
+    /// type, the gpu would read the buffers at the wrong offsets otherwise. The modules that don't
+    /// use `vfx::particle` are skipped, the tests make sure the struct is found when it is.
+    pub fn check_layout(module: &naga::Module) {
+        let name = naga_oil::compose::Composer::decorated_name(Some("vfx::particle"), "Particle");
+        let Some((members, span)) = module.types.iter().find_map(|(_, ty)| match &ty.inner {
+            naga::TypeInner::Struct { members, span } if ty.name.as_deref() == Some(&name) => {
+                Some((members, *span))
+            }
+            _ => None,
+        }) else {
+            return;
+        };
+
+        assert_eq!(
+            span as usize,
+            size_of::<Particle>(),
+            "the wgsl `Particle` and the rust one have different sizes"
+        );
+        assert_eq!(members.len(), FIELDS.len(), "`Particle` fields differ");
+        for (member, field) in members.iter().zip(&FIELDS) {
+            assert_eq!(
+                (member.name.as_deref(), member.offset as usize),
+                (Some(field.name), field.offset),
+                "`Particle` (name, offset) differ between wgsl and rust"
+            );
+            assert!(
+                field.ty.matches(&module.types[member.ty].inner),
+                "`Particle::{}` is not a {} in wgsl",
+                field.name,
+                field.ty.wgsl()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wgsl_layout_matches_rust() {
+        let module = crate::attr::ShaderBuilder::build_module(
+            "#import vfx::particle::Particle
+
+            @group(0) @binding(0) var<storage, read> particles: array<Particle>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+              _ = particles[0];
+            }",
+        );
+        let name = naga_oil::compose::Composer::decorated_name(Some("vfx::particle"), "Particle");
+        let (members, span) = module
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.inner {
+                naga::TypeInner::Struct { members, span } if ty.name.as_deref() == Some(&name) => {
+                    Some((members, *span))
+                }
+                _ => None,
+            })
+            .expect("the composed module has no `vfx::particle::Particle`");
+
+        assert_eq!(span as usize, size_of::<Particle>());
+        assert_eq!(members.len(), FIELDS.len());
+        for (member, field) in members.iter().zip(&FIELDS) {
+            let size = match field.ty {
+                FieldType::F32 | FieldType::U32 => 4,
+                FieldType::Vec3f => 12,
+                FieldType::Vec4f => 16,
+            };
+            assert_eq!(member.name.as_deref(), Some(field.name));
+            assert_eq!(
+                member.offset as usize, field.offset,
+                "offset of {}",
+                field.name
+            );
+            assert_eq!(
+                module.types[member.ty].inner.size(module.to_ctx()) as usize,
+                size,
+                "size of {}",
+                field.name
+            );
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! CPU reference of the `vfx::rand` shader module: a [`Pcg`] created with the same index, frame
+//! and seed produces the same numbers as `init_rand` followed by `rand_u32`/`rand` on the GPU.
+
+/// PCG output permutation, used to hash the seeds into a state.
+pub fn pcg_hash(input: u32) -> u32 {
+    let state = input.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// 32 bits PCG (RXS-M-XS variant).
+#[derive(Debug, Clone)]
+pub struct Pcg {
+    state: u32,
+}
+
+impl Pcg {
+    /// Same as `init_rand(index, frame, seed)`.
+    pub fn new(index: u32, frame: u32, seed: u32) -> Self {
+        Self {
+            state: pcg_hash(index.wrapping_add(pcg_hash(frame.wrapping_add(pcg_hash(seed))))),
+        }
+    }
+
+    /// Same as `rand_u32()`.
+    pub fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.state = state.wrapping_mul(747796405).wrapping_add(2891336453);
+        let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+        (word >> 22) ^ word
+    }
+
+    /// Same as `rand()`, uniform in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / 16777216.0
+    }
+
+    /// Same as `gen_range(min, max)`.
+    pub fn gen_range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+/// Lets the `rand` distributions run on top of the PCG, the particles generated on the CPU are
+/// deterministic as well.
+impl rand::RngCore for Pcg {
+    fn next_u32(&mut self) -> u32 {
+        Pcg::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (Pcg::next_u32(self) as u64) << 32 | Pcg::next_u32(self) as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = Pcg::next_u32(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn known_outputs() {
+        let mut rng = Pcg::new(0, 0, 0);
+        let outputs = [(); 3].map(|_| rng.next_u32());
+        assert_eq!(outputs, [3791710391, 2368882721, 2659002260]);
+
+        let mut rng = Pcg::new(7, 3, 42);
+        let outputs = [(); 3].map(|_| rng.next_u32());
+        assert_eq!(outputs, [28064990, 933944000, 192148435]);
+    }
+
+    #[test]
+    fn rand_is_in_unit_range() {
+        for index in 0..100 {
+            let mut rng = Pcg::new(index, 1, 42);
+            for _ in 0..100 {
+                let x = rng.next_f32();
+                assert!((0.0..1.0).contains(&x), "{x} out of [0, 1)");
+            }
+        }
+        // the largest value kept by the shift still rounds below 1.
+        assert!(((u32::MAX >> 8) as f32 / 16777216.0) < 1.0);
+    }
+
+    #[test]
+    fn indices_have_their_own_streams() {
+        // particles sharing a position and a step used to draw the same numbers.
+        let streams: HashSet<[u32; 4]> = (0..10000)
+            .map(|index| {
+                let mut rng = Pcg::new(index, 5, 42);
+                [(); 4].map(|_| rng.next_u32())
+            })
+            .collect();
+        assert_eq!(streams.len(), 10000);
+
+        let firsts: HashSet<u32> = (0..1000)
+            .map(|index| Pcg::new(index, 5, 42).next_u32())
+            .collect();
+        assert_eq!(firsts.len(), 1000);
+
+        let mut a = Pcg::new(3, 5, 42);
+        let mut b = Pcg::new(3, 5, 42);
+        assert!((0..16).all(|_| a.next_u32() == b.next_u32()));
+    }
+}
@@ -1,6 +1,10 @@
+use crate::emitter::BloomSettings;
 use crate::texture::{self, create_bind_group_texture_layout};
+use crate::uniform::Uniform;
 
 pub struct Bloom {
+    pub enabled: bool,
+    pub uniform: Uniform<BloomUniform>,
     brightness_target_texture: texture::Texture,
     horizontal_blur_target_texture: texture::Texture,
     vertical_blur_target_texture: texture::Texture,
@@ -12,7 +16,16 @@ pub struct Bloom {
 }
 
 impl Bloom {
-    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, size: (u32, u32)) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: (u32, u32),
+        settings: &BloomSettings,
+    ) -> Self {
+        let mut uniform = Uniform::<BloomUniform>::new(device);
+        uniform.data.threshold = settings.threshold;
+        uniform.data.intensity = settings.intensity;
+
         let brightness_target_texture = texture::Texture::empty(&device, size, Some("offscreen"))
             .expect("Failed to build empty texture");
 
@@ -34,10 +47,20 @@ impl Bloom {
             bind_group_layouts: &[&bind_group_layout],
             push_constant_ranges: &[],
         });
+        let brightness_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout, &uniform.bind_group_layout],
+                push_constant_ranges: &[],
+            });
         let final_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: None,
-                bind_group_layouts: &[&bind_group_layout, &bind_group_layout],
+                bind_group_layouts: &[
+                    &bind_group_layout,
+                    &bind_group_layout,
+                    &uniform.bind_group_layout,
+                ],
                 push_constant_ranges: &[],
             });
         let shader_fullscreen_quad = device.create_shader_module(wgpu::include_wgsl!(
@@ -50,7 +73,7 @@ impl Bloom {
         let shader_blur = device.create_shader_module(wgpu::include_wgsl!("../shaders/blur.wgsl"));
         let brightness_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Brightness pipeline"),
-            layout: Some(&pipeline_layout),
+            layout: Some(&brightness_pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader_fullscreen_quad,
                 entry_point: Some("vs_main"),
@@ -155,6 +178,8 @@ impl Bloom {
         });
 
         Self {
+            enabled: settings.enabled,
+            uniform,
             brightness_pipeline,
             horizontal_blur_pipeline,
             vertical_blur_pipeline,
@@ -189,6 +214,7 @@ impl Bloom {
 
         brightness_rpass.set_pipeline(&self.brightness_pipeline);
         brightness_rpass.set_bind_group(0, &scene_texture.bind_group, &[]);
+        brightness_rpass.set_bind_group(1, &self.uniform.bind_group, &[]);
         brightness_rpass.draw(0..6, 0..1);
 
         drop(brightness_rpass);
@@ -252,6 +278,25 @@ impl Bloom {
         final_rpass.set_pipeline(&self.blend_pipeline);
         final_rpass.set_bind_group(0, &self.horizontal_blur_target_texture.bind_group, &[]);
         final_rpass.set_bind_group(1, &scene_texture.bind_group, &[]);
+        final_rpass.set_bind_group(2, &self.uniform.bind_group, &[]);
         final_rpass.draw(0..6, 0..1);
     }
 }
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BloomUniform {
+    pub threshold: f32,
+    pub intensity: f32,
+    _pad: [f32; 2],
+}
+
+impl Default for BloomUniform {
+    fn default() -> Self {
+        Self {
+            threshold: 0.7,
+            intensity: 5.0,
+            _pad: [0.0; 2],
+        }
+    }
+}
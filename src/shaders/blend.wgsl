@@ -10,4 +10,12 @@ fn fs_main(@location(0) tex_coords : vec2<f32>) -> @location(0) vec4f {
     let particles_color = textureSample(particles_texture, particles_sampler, tex_coords);
 
     return common_color.rgba + particles_color.rgba;
+}
+
+// without bloom, its texture was never drawn. The particles don't write the alpha of the scene,
+// the frame is opaque.
+@fragment
+fn fs_copy(@location(0) tex_coords : vec2<f32>) -> @location(0) vec4f {
+    let common_color = textureSample(common_texture, common_sampler, tex_coords);
+    return vec4f(common_color.rgb, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,106 @@
+#import vfx::particle::{Particle, WORKGROUP_SIZE, thread_index}
+#import vfx::grid::{GridParams, cell_hash, cell_of}
+
+// the threads of `grid_scan`, each one sums a slice of the table.
+const SCAN_SIZE: u32 = 256u;
+
+struct ParticleList {
+  count: u32,
+  indices: array<u32>,
+}
+
+// `GridCell` while it's built: `end` counts the particles, then it's the cursor of `grid_scatter`.
+struct Cell {
+  start: u32,
+  end: atomic<u32>,
+}
+
+@binding(0) @group(0) var<uniform> params: GridParams;
+@binding(1) @group(0) var<storage, read> alive_list: ParticleList;
+@binding(2) @group(0) var<storage, read> particles: array<Particle>; // `particles_src` of the step
+@binding(3) @group(0) var<storage, read_write> cells: array<Cell>;
+@binding(4) @group(0) var<storage, read_write> indices: array<u32>;
+
+fn alive_cell(thread: u32) -> u32 {
+  let position = particles[alive_list.indices[thread]].position.xyz;
+  return cell_hash(cell_of(position, params.cell_size), params.cells);
+}
+
+// empties every cell, one thread per cell.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn grid_clear(
+  @builtin(global_invocation_id) global_invocation_id: vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+  let cell = thread_index(global_invocation_id, num_workgroups);
+  if (cell >= params.cells) {
+    return;
+  }
+  cells[cell].start = 0u;
+  atomicStore(&cells[cell].end, 0u);
+}
+
+// counts the live particles of every cell, one thread per particle.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn grid_count(
+  @builtin(global_invocation_id) global_invocation_id: vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+  let thread = thread_index(global_invocation_id, num_workgroups);
+  if (thread >= alive_list.count) {
+    return;
+  }
+  atomicAdd(&cells[alive_cell(thread)].end, 1u);
+}
+
+var<workgroup> sums: array<u32, SCAN_SIZE>;
+
+// exclusive prefix sum of the counts into `start` on a single workgroup, `end` restarts from
+// `start`.
+@compute @workgroup_size(SCAN_SIZE)
+fn grid_scan(@builtin(local_invocation_index) thread: u32) {
+  let slice = (params.cells + SCAN_SIZE - 1u) / SCAN_SIZE;
+  let begin = min(thread * slice, params.cells);
+  let end = min(begin + slice, params.cells);
+
+  var sum = 0u;
+  for (var i = begin; i < end; i++) {
+    sum += atomicLoad(&cells[i].end);
+  }
+  sums[thread] = sum;
+  workgroupBarrier();
+
+  // inclusive scan of the slices.
+  for (var offset = 1u; offset < SCAN_SIZE; offset *= 2u) {
+    var previous = 0u;
+    if (thread >= offset) {
+      previous = sums[thread - offset];
+    }
+    workgroupBarrier();
+    sums[thread] += previous;
+    workgroupBarrier();
+  }
+
+  var start = sums[thread] - sum;
+  for (var i = begin; i < end; i++) {
+    let count = atomicLoad(&cells[i].end);
+    cells[i].start = start;
+    atomicStore(&cells[i].end, start);
+    start += count;
+  }
+}
+
+// sorts the live particles by cell, `end` is back to the end of the cell once they are all
+// written.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn grid_scatter(
+  @builtin(global_invocation_id) global_invocation_id: vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+  let thread = thread_index(global_invocation_id, num_workgroups);
+  if (thread >= alive_list.count) {
+    return;
+  }
+  let slot = atomicAdd(&cells[alive_cell(thread)].end, 1u);
+  indices[slot] = alive_list.indices[thread];
+}
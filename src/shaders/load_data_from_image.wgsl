@@ -1,27 +1,88 @@
+#import vfx::particle::Particle
 
-struct Particle {
-  position: vec4f,
-  dir: vec3f,
-  velocity: f32,
-  color: vec4<f32>,
+// `ImageGrid` and `ImageOptions` of the emitter, see `emitter::loader`.
+struct LoaderParams {
+  grid: vec2u, // particles along x and y
+  size: vec2f, // size in pixels of the image the positions are relative to
+  stride: u32, // 0 averages the texels of every cell, otherwise one texel every `stride`
+  alpha_threshold: f32,
+  depth: f32,
+  scale: f32,
+  keep_color: u32, // the colors were set from `initial.color`
+  keep_target: u32, // the morph targets were set from the `target` shape
 }
 
-@binding(0) @group(0) var<storage, read_write> particles_dst : array<Particle>;
+// only `count` is used, the indices are already `0..capacity`.
+struct ParticleList {
+  count: atomic<u32>,
+  indices: array<u32>,
+}
+
+@binding(0) @group(0) var<uniform> params: LoaderParams;
+@binding(1) @group(0) var image: texture_2d<f32>;
+@binding(2) @group(0) var<storage, read_write> particles_dst: array<Particle>;
+@binding(3) @group(0) var<storage, read_write> list: ParticleList; // the list the particles start in
 
-@compute @workgroup_size(64)
+// the mean of the texels covered by `cell` when the image is scaled down to the grid, every cell
+// covers at least one texel.
+fn average(cell: vec2u) -> vec4f {
+  let size = textureDimensions(image);
+  let start = cell * size / params.grid;
+  let end = max((cell + 1u) * size / params.grid, start + 1u);
+  var sum = vec4f(0.0);
+  for (var y = start.y; y < end.y; y++) {
+    for (var x = start.x; x < end.x; x++) {
+      sum += textureLoad(image, vec2u(x, y), 0);
+    }
+  }
+  let footprint = end - start;
+  return sum / f32(footprint.x * footprint.y);
+}
+
+// one thread per cell of the grid, the kept pixels are packed at the front of `particles_dst` and
+// counted in `list`.
+@compute @workgroup_size(8, 8)
 fn loader(@builtin(global_invocation_id) global_invocation_id: vec3u) {
-    let idx = global_invocation_id.x;
+    let cell = global_invocation_id.xy;
+    if (any(cell >= params.grid)) {
+      return;
+    }
 
-    if (idx >= total) {
+    var color: vec4f;
+    var pixel: vec2f;
+    if (params.stride == 0u) {
+      pixel = vec2f(cell) + 0.5;
+      color = average(cell);
+    } else {
+      let texel = cell * params.stride;
+      pixel = vec2f(texel) + 0.5;
+      color = textureLoad(image, texel, 0);
+    }
+    if (color.a < params.alpha_threshold) {
       return;
     }
-    
+
+    let idx = atomicAdd(&list.count, 1u);
     var particle: Particle = particles_dst[idx];
-    
-    particle.position = vec4(0.0, 0.0, 0.0, 1.0);
-    particle.dir = vec3(0.0, 1.0, 1.0);
-    particle.velocity = 1.0;
-    particle.color = vec4(1.0,1.0,0.0,1.0);
+
+    // the longest side goes from -scale to scale, the other one keeps the aspect ratio.
+    let extent = max(params.size.x, params.size.y) / 2.0;
+    let world = (pixel - params.size / 2.0) / extent;
+    let luminance = dot(color.rgb, vec3f(0.2126, 0.7152, 0.0722));
+    // the rows of the image go down, the y axis goes up.
+    let origin = vec3f(vec2f(world.x, -world.y) * params.scale, luminance * params.depth);
+
+    particle.position = vec4f(origin, particle.position.w);
+    particle.origin = origin;
+    particle.prev_position = origin;
+    if (params.keep_color == 0u) {
+      particle.color = color;
+      particle.origin_color = pack4x8unorm(color);
+    }
+    if (params.keep_target == 0u) {
+      particle.morph_target = origin;
+      particle.target_color = particle.origin_color;
+    }
 
     particles_dst[idx] = particle;
-}
\ No newline at end of file
+}
@@ -0,0 +1,23 @@
+#define_import_path vfx::attractor
+
+#import vfx::particle::{Particle, SimulationParams, get_velocity, set_velocity}
+
+struct Params {
+  position: vec3f,
+  strength: f32, // negative values repel
+  radius: f32,
+  falloff: f32,
+}
+
+fn update(particle: ptr<function, Particle>, params: Params, sim: SimulationParams) {
+  let to_center = params.position - (*particle).position.xyz;
+  let distance = length(to_center);
+  if (distance >= params.radius || distance < 1e-4) {
+    return;
+  }
+
+  // full strength at the center, nothing at the radius.
+  let weight = pow(1.0 - distance / params.radius, params.falloff);
+  let acceleration = to_center / distance * params.strength * weight;
+  set_velocity(particle, get_velocity(*particle) + acceleration * sim.delta_time);
+}
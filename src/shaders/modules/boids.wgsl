@@ -0,0 +1,72 @@
+#define_import_path vfx::boids
+
+#import vfx::particle::{Particle, SimulationParams, get_velocity, set_velocity}
+#import vfx::grid::{neighbors, next_neighbor, neighbor}
+#import vfx::collider::{colliders, surface}
+
+struct Params {
+  goal: vec3f, // the `target` in rust, it's a reserved word in wgsl
+  seek: f32, // 0 ignores the goal
+  separation: f32,
+  alignment: f32,
+  cohesion: f32,
+  avoidance: f32,
+  view_radius: f32, // at most the cell size of the grid
+  max_speed: f32,
+  min_speed: f32,
+}
+
+fn update(particle: ptr<function, Particle>, params: Params, sim: SimulationParams) {
+  let position = (*particle).position.xyz;
+  let velocity = get_velocity(*particle);
+
+  var count = 0.0;
+  var center = vec3f(0.0);
+  var heading = vec3f(0.0);
+  var away = vec3f(0.0);
+  var it = neighbors(position);
+  while (next_neighbor(&it)) {
+    let other = neighbor(it);
+    let r = position - other.position.xyz;
+    let d = length(r);
+    // the particle itself is at distance 0.
+    if (d >= params.view_radius || d == 0.0) {
+      continue;
+    }
+    count += 1.0;
+    center += other.position.xyz;
+    heading += get_velocity(other);
+    // the closest neighbors push the hardest.
+    away += r / (d * d);
+  }
+
+  var steer = vec3f(0.0);
+  if (count > 0.0) {
+    steer += params.separation * away;
+    steer += params.alignment * (heading / count - velocity);
+    steer += params.cohesion * (center / count - position);
+  }
+
+  // turns away from the colliders closer than the view radius, harder as they get closer.
+  for (var i = 0u; i < colliders.count; i++) {
+    let s = surface(position, colliders.colliders[i]);
+    if (s.w < params.view_radius) {
+      steer += params.avoidance * (1.0 - max(s.w, 0.0) / params.view_radius) * s.xyz;
+    }
+  }
+
+  let to_goal = params.goal - position;
+  let distance = length(to_goal);
+  if (distance > 1e-4) {
+    steer += params.seek * (to_goal / distance * params.max_speed - velocity);
+  }
+
+  var v = velocity + steer * sim.delta_time;
+  let speed = length(v);
+  if (speed > params.max_speed) {
+    v *= params.max_speed / speed;
+  } else if (speed < params.min_speed && speed > 0.0) {
+    v *= params.min_speed / speed;
+  }
+  set_velocity(particle, v);
+}
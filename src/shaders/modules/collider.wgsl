@@ -0,0 +1,114 @@
+#define_import_path vfx::collider
+#import vfx::particle::{Particle, get_velocity, set_velocity}
+
+const PLANE: u32 = 0u;
+const SPHERE: u32 = 1u;
+const BOX: u32 = 2u;
+// resolved by `collide_depth` in the compute shader, it reads the depth prepass.
+const DEPTH: u32 = 3u;
+
+// `collider::ColliderParams` in rust.
+struct Collider {
+  center: vec3f, // a point of the plane, or the center of the sphere and of the box
+  kind: u32,
+  // the normal of the plane, the size of the box, or the radius of the sphere and the thickness of
+  // the depth in x
+  size: vec3f,
+  restitution: f32,
+  rotation: vec4f, // quaternion of the box
+  friction: f32,
+  kill: u32,
+  inside: u32, // the particles are kept inside the sphere or the box
+}
+
+struct Colliders {
+  colliders: array<Collider, 16>,
+  count: u32,
+}
+
+// the colliders of the emitter, only the first `count` are used.
+@binding(0) @group(3) var<uniform> colliders: Colliders;
+
+fn rotate(q: vec4f, v: vec3f) -> vec3f {
+  return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
+// moves a particle by `offset` back to the surface of normal `n` and bounces its velocity off it.
+fn resolve(particle: ptr<function, Particle>, offset: vec3f, n: vec3f, collider: Collider) {
+  (*particle).position += vec4f(offset, 0.0);
+  let velocity = get_velocity(*particle);
+  let vn = dot(velocity, n);
+  if (vn < 0.0) {
+    let tangent = velocity - vn * n;
+    set_velocity(particle, tangent * (1.0 - collider.friction) - vn * collider.restitution * n);
+  }
+}
+
+// the signed distance from a position to the surface of a collider in `w`, negative inside it,
+// and the normal pointing out of it in `xyz`. The depth is never reached.
+fn surface(position: vec3f, collider: Collider) -> vec4f {
+  var d: f32;
+  var n: vec3f;
+  switch collider.kind {
+    case PLANE: {
+      n = collider.size;
+      d = dot(position - collider.center, n);
+    }
+    case SPHERE: {
+      let r = position - collider.center;
+      let len = length(r);
+      n = select(vec3f(0.0, 1.0, 0.0), r / len, len > 0.0);
+      d = len - collider.size.x;
+    }
+    case BOX: {
+      let inverse = vec4f(-collider.rotation.xyz, collider.rotation.w);
+      let local = rotate(inverse, position - collider.center);
+      let s = select(vec3f(-1.0), vec3f(1.0), local >= vec3f(0.0));
+      let q = abs(local) - collider.size / 2.0;
+      var normal: vec3f;
+      if (all(q <= vec3f(0.0))) {
+        // inside, the closest face wins.
+        if (q.x >= q.y && q.x >= q.z) {
+          normal = vec3f(s.x, 0.0, 0.0);
+          d = q.x;
+        } else if (q.y >= q.z) {
+          normal = vec3f(0.0, s.y, 0.0);
+          d = q.y;
+        } else {
+          normal = vec3f(0.0, 0.0, s.z);
+          d = q.z;
+        }
+      } else {
+        let outside = max(q, vec3f(0.0));
+        d = length(outside);
+        normal = s * outside / d;
+      }
+      n = rotate(collider.rotation, normal);
+    }
+    default: {
+      return vec4f(0.0, 0.0, 0.0, 3.4e38);
+    }
+  }
+  if (collider.inside != 0u && collider.kind != PLANE) {
+    d = -d;
+    n = -n;
+  }
+  return vec4f(n, d);
+}
+
+// pushes a particle inside the collider back to its surface and bounces its velocity off it,
+// returns true when the particle must die instead. The depth is not handled here.
+fn collide(particle: ptr<function, Particle>, collider: Collider) -> bool {
+  let s = surface((*particle).position.xyz, collider);
+  let n = s.xyz;
+  let d = s.w;
+  if (d >= 0.0) {
+    return false;
+  }
+  if (collider.kill != 0u) {
+    return true;
+  }
+
+  resolve(particle, -d * n, n, collider);
+  return false;
+}
@@ -0,0 +1,16 @@
+#define_import_path vfx::drag
+
+#import vfx::particle::{Particle, SimulationParams, get_velocity, set_velocity}
+
+struct Params {
+  linear: f32,
+  quadratic: f32,
+}
+
+fn update(particle: ptr<function, Particle>, params: Params, sim: SimulationParams) {
+  // the drag acts on the velocity relative to the air, it pulls the particles toward the wind.
+  let relative = get_velocity(*particle) - sim.wind;
+  let k = params.linear + params.quadratic * length(relative);
+  // implicit step, a strong drag slows the particle down but never reverses it.
+  set_velocity(particle, sim.wind + relative / (1.0 + k * sim.delta_time));
+}
@@ -0,0 +1,11 @@
+#define_import_path vfx::gravity
+
+#import vfx::particle::{Particle, SimulationParams, get_velocity, set_velocity}
+
+struct Params {
+  acceleration: vec3f,
+}
+
+fn update(particle: ptr<function, Particle>, params: Params, sim: SimulationParams) {
+  set_velocity(particle, get_velocity(*particle) + params.acceleration * sim.delta_time);
+}
@@ -0,0 +1,87 @@
+#define_import_path vfx::grid
+
+#import vfx::particle::Particle
+
+// `GridParams` in rust.
+struct GridParams {
+  cell_size: f32, // the neighbors of a particle are at most `cell_size` away in every axis
+  cells: u32, // entries of the hash table, the cells of space share them
+}
+
+// the particles of a cell are `grid_indices[start..end]`.
+struct GridCell {
+  start: u32,
+  end: u32,
+}
+
+// the particles at the start of the step, `simulate` writes them to `particles_dst` in ping-pong.
+// Neighbors are read here, they don't move while the step runs.
+@binding(8) @group(0) var<storage, read> particles_src: array<Particle>;
+// built before `simulate` by `grid.wgsl` from the particles of `particles_src`.
+@binding(11) @group(0) var<uniform> grid_params: GridParams;
+@binding(12) @group(0) var<storage, read> grid_cells: array<GridCell>;
+@binding(13) @group(0) var<storage, read> grid_indices: array<u32>;
+
+fn cell_of(position: vec3f, cell_size: f32) -> vec3i {
+  return vec3i(floor(position / cell_size));
+}
+
+// the entry of the hash table of a cell.
+fn cell_hash(cell: vec3i, cells: u32) -> u32 {
+  let c = bitcast<vec3u>(cell);
+  return ((c.x * 73856093u) ^ (c.y * 19349663u) ^ (c.z * 83492791u)) % cells;
+}
+
+// walks the particles of the 27 cells around a position, the particle itself included:
+//
+//   var it = neighbors((*particle).position.xyz);
+//   while (next_neighbor(&it)) {
+//     let other = neighbor(it);
+//   }
+//
+// An entry of the hash table shared by several of the 27 cells is walked once, but the cells
+// farther away sharing it still give their particles: keep the ones closer than
+// `grid_params.cell_size`.
+struct Neighbors {
+  center: vec3i,
+  cell: u32, // the next of the 27 cells
+  next: u32, // the next slot of `grid_indices` in the current cell
+  end: u32,
+  index: u32, // the index of the current neighbor in `particles_src`
+  entries: array<u32, 27>, // the entries of the hash table of the cells already walked
+}
+
+fn neighbors(position: vec3f) -> Neighbors {
+  return Neighbors(cell_of(position, grid_params.cell_size), 0u, 0u, 0u, 0u, array<u32, 27>());
+}
+
+// moves to the next neighbor, false once they were all visited.
+fn next_neighbor(it: ptr<function, Neighbors>) -> bool {
+  while ((*it).next >= (*it).end) {
+    let c = (*it).cell;
+    if (c == 27u) {
+      return false;
+    }
+    let offset = vec3i(i32(c % 3u), i32(c / 3u % 3u), i32(c / 9u)) - 1;
+    let entry = cell_hash((*it).center + offset, grid_params.cells);
+    (*it).entries[c] = entry;
+    (*it).cell = c + 1u;
+    var walked = false;
+    for (var i = 0u; i < c; i++) {
+      walked = walked || (*it).entries[i] == entry;
+    }
+    if (walked) {
+      continue;
+    }
+    let cell = grid_cells[entry];
+    (*it).next = cell.start;
+    (*it).end = cell.end;
+  }
+  (*it).index = grid_indices[(*it).next];
+  (*it).next += 1u;
+  return true;
+}
+
+fn neighbor(it: Neighbors) -> Particle {
+  return particles_src[it.index];
+}
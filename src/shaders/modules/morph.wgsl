@@ -0,0 +1,65 @@
+#define_import_path vfx::morph
+
+#import vfx::particle::{Particle, SimulationParams, PI, set_velocity}
+#import vfx::rand::pcg_hash
+
+struct Params {
+  progress: f32, // 0 at the origin, 1 at the target
+  easing: u32,
+  delay: f32, // share of the morph spent waiting, each particle waits `morph_delay` of it
+  noise: f32, // distance the particles stray from the straight path halfway through
+}
+
+// `Easing` in `attr/morph.rs`.
+const LINEAR: u32 = 0u;
+const SMOOTH_STEP: u32 = 1u;
+const CUBIC_IN_OUT: u32 = 2u;
+const BACK_OUT: u32 = 3u;
+
+fn ease(t: f32, easing: u32) -> f32 {
+  switch easing {
+    case SMOOTH_STEP: {
+      return t * t * (3.0 - 2.0 * t);
+    }
+    case CUBIC_IN_OUT: {
+      if (t < 0.5) {
+        return 4.0 * t * t * t;
+      }
+      return 1.0 - pow(-2.0 * t + 2.0, 3.0) / 2.0;
+    }
+    case BACK_OUT: {
+      let c1 = 1.70158;
+      let c3 = c1 + 1.0;
+      return 1.0 + c3 * pow(t - 1.0, 3.0) + c1 * pow(t - 1.0, 2.0);
+    }
+    default: {
+      return t;
+    }
+  }
+}
+
+// a direction in [-1, 1]³ that never changes for a given particle.
+fn stray_direction(particle: Particle) -> vec3f {
+  let h0 = pcg_hash(bitcast<u32>(particle.morph_delay));
+  let h1 = pcg_hash(h0);
+  let h2 = pcg_hash(h1);
+  return vec3f(vec3u(h0, h1, h2) >> vec3u(8u)) / 8388608.0 - 1.0;
+}
+
+// places the particle on the path from its origin to its target, the velocity is set so the
+// integration of `simulate` lands exactly on it.
+fn update(particle: ptr<function, Particle>, params: Params, sim: SimulationParams) {
+  let start = (*particle).morph_delay * params.delay;
+  let t = clamp((params.progress - start) / max(1.0 - params.delay, 1e-4), 0.0, 1.0);
+  let e = ease(t, params.easing);
+
+  let stray = stray_direction(*particle) * params.noise * sin(PI * clamp(e, 0.0, 1.0));
+  let position = mix((*particle).origin, (*particle).morph_target, e) + stray;
+  if (sim.delta_time > 0.0) {
+    set_velocity(particle, (position - (*particle).position.xyz) / sim.delta_time);
+  }
+
+  let origin_color = unpack4x8unorm((*particle).origin_color);
+  let target_color = unpack4x8unorm((*particle).target_color);
+  (*particle).color = mix(origin_color, target_color, clamp(e, 0.0, 1.0));
+}
@@ -0,0 +1,51 @@
+#define_import_path vfx::particle
+
+const PI: f32 = 3.14159265358;
+
+// the global parameters of a step, shared by every emitter. `SimulationParams` in rust.
+struct SimulationParams {
+  delta_time: f32,
+  time: f32, // seconds since the system was created
+  frame: u32, // steps simulated, starting at 1
+  seed: u32, // seed of the scene
+  gravity: vec3f, // acceleration of every particle, on top of the `gravity` modules
+  wind: vec3f, // velocity of the air, the `drag` modules slow the particles relative to it
+  bounds_min: vec3f, // the particles leaving the bounds die, they are infinite when not set
+  bounds_max: vec3f,
+}
+
+// threads of the `emit` and `simulate` workgroups.
+const WORKGROUP_SIZE: u32 = 64u;
+// dispatches of more workgroups are laid out in 2D, it's the `max_compute_workgroups_per_dimension`
+// guaranteed by wgpu.
+const MAX_WORKGROUPS: u32 = 65535u;
+
+// the workgroups needed by `count` threads, `x` is filled before `y`. Same as
+// `emitter::dispatch_size` in rust.
+fn dispatch_size(count: u32) -> vec3u {
+  let workgroups = (count + WORKGROUP_SIZE - 1u) / WORKGROUP_SIZE;
+  let x = max(min(workgroups, MAX_WORKGROUPS), 1u);
+  return vec3u(x, (workgroups + x - 1u) / x, 1u);
+}
+
+// linear index of the thread in a dispatch laid out by `dispatch_size`, some threads of the last
+// row are past the end.
+fn thread_index(global_invocation_id: vec3u, num_workgroups: vec3u) -> u32 {
+  return global_invocation_id.x + global_invocation_id.y * num_workgroups.x * WORKGROUP_SIZE;
+}
+
+// generated from `particle::Particle` in rust.
+;;PARTICLE_STRUCT
+
+// the velocity of a particle is stored as a direction and a speed.
+fn get_velocity(particle: Particle) -> vec3f {
+  return particle.dir * particle.velocity;
+}
+
+fn set_velocity(particle: ptr<function, Particle>, velocity: vec3f) {
+  let speed = length(velocity);
+  if (speed > 0.0) {
+    (*particle).dir = velocity / speed;
+  }
+  (*particle).velocity = speed;
+}
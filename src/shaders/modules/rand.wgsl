@@ -0,0 +1,31 @@
+#define_import_path vfx::rand
+
+// 32 bits PCG, `src/pcg.rs` is the CPU reference of this module.
+var<private> rng_state: u32;
+
+fn pcg_hash(input: u32) -> u32 {
+  let state = input * 747796405u + 2891336453u;
+  let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+  return (word >> 22u) ^ word;
+}
+
+// every (index, frame, seed) triple starts an independent stream.
+fn init_rand(index: u32, frame: u32, seed: u32) {
+  rng_state = pcg_hash(index + pcg_hash(frame + pcg_hash(seed)));
+}
+
+fn rand_u32() -> u32 {
+  let state = rng_state;
+  rng_state = state * 747796405u + 2891336453u;
+  let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+  return (word >> 22u) ^ word;
+}
+
+// uniform in [0, 1)
+fn rand() -> f32 {
+  return f32(rand_u32() >> 8u) / 16777216.0;
+}
+
+fn gen_range(min: f32, max: f32) -> f32 {
+  return min + (max - min) * rand();
+}
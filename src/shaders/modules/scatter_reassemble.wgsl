@@ -0,0 +1,23 @@
+#define_import_path vfx::scatter_reassemble
+
+#import vfx::particle::{Particle, SimulationParams}
+#import vfx::rand::gen_range
+
+struct Params {
+  velocity: f32,
+}
+
+fn update(particle: ptr<function, Particle>, params: Params, sim: SimulationParams) {
+  if (params.velocity >= 0.0) {
+      let d = normalize(vec3f(gen_range(-1.0, 1.0), gen_range(-1.0, 1.0), gen_range(-1.0, 1.0))); // Random direction
+      let force = clamp(1.0 / (length(d) + 1.0), 0.1, 5.0); // Avoid division by zero
+
+      (*particle).dir = d * force; // Move outward in a random direction
+  } else {
+      let ndir = normalize((*particle).origin - (*particle).position.xyz);
+      let force = length((*particle).origin - (*particle).position.xyz) * 0.05; // Scale force based on distance
+      (*particle).dir = ndir * force;
+  }
+
+  (*particle).velocity = abs(params.velocity) * 2.0;
+}
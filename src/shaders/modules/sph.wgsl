@@ -0,0 +1,48 @@
+#define_import_path vfx::sph
+
+// the smoothing kernels of Müller et al. 2003, `h` is the smoothing radius and they are zero past
+// it. `src/sph.rs` is their cpu reference.
+
+const PI: f32 = 3.14159265;
+
+// `FluidParams` in rust.
+struct FluidParams {
+  rest_density: f32,
+  stiffness: f32, // pressure per unit of density above `rest_density`
+  viscosity: f32,
+  surface_tension: f32,
+  mass: f32, // of every particle
+}
+
+// the density kernel, from the squared distance.
+fn poly6(r2: f32, h: f32) -> f32 {
+  let h2 = h * h;
+  if (r2 >= h2) {
+    return 0.0;
+  }
+  let d = h2 - r2;
+  return 315.0 / (64.0 * PI * pow(h, 9.0)) * d * d * d;
+}
+
+// gradient of the pressure kernel at `r`, `d` is its length.
+fn spiky_gradient(r: vec3f, d: f32, h: f32) -> vec3f {
+  if (d >= h || d <= 0.0) {
+    return vec3f(0.0);
+  }
+  let x = h - d;
+  return -45.0 / (PI * pow(h, 6.0)) * x * x * r / d;
+}
+
+// laplacian of the viscosity kernel at the distance `d`.
+fn viscosity_laplacian(d: f32, h: f32) -> f32 {
+  if (d >= h) {
+    return 0.0;
+  }
+  return 45.0 / (PI * pow(h, 6.0)) * (h - d);
+}
+
+// the fluid only pushes: below the rest density the pressure is zero, `surface_tension` holds the
+// particles of the surface together instead.
+fn pressure(density: f32, params: FluidParams) -> f32 {
+  return params.stiffness * max(density - params.rest_density, 0.0);
+}
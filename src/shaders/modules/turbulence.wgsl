@@ -0,0 +1,51 @@
+#define_import_path vfx::turbulence
+
+#import vfx::particle::{Particle, SimulationParams, get_velocity, set_velocity}
+
+struct Params {
+  strength: f32,
+  frequency: f32,
+  speed: f32, // how fast the noise field scrolls
+}
+
+fn hash(p: vec3f) -> f32 {
+  let q = fract(p * 0.3183099 + 0.1) * 17.0;
+  return fract(q.x * q.y * q.z * (q.x + q.y + q.z));
+}
+
+// value noise in [-1, 1]
+fn noise(p: vec3f) -> f32 {
+  let i = floor(p);
+  let f = fract(p);
+  let u = f * f * (3.0 - 2.0 * f);
+
+  let x00 = mix(hash(i), hash(i + vec3f(1.0, 0.0, 0.0)), u.x);
+  let x10 = mix(hash(i + vec3f(0.0, 1.0, 0.0)), hash(i + vec3f(1.0, 1.0, 0.0)), u.x);
+  let x01 = mix(hash(i + vec3f(0.0, 0.0, 1.0)), hash(i + vec3f(1.0, 0.0, 1.0)), u.x);
+  let x11 = mix(hash(i + vec3f(0.0, 1.0, 1.0)), hash(i + vec3f(1.0, 1.0, 1.0)), u.x);
+  return mix(mix(x00, x10, u.y), mix(x01, x11, u.y), u.z) * 2.0 - 1.0;
+}
+
+// three decorrelated noises used as a vector potential.
+fn potential(p: vec3f) -> vec3f {
+  return vec3f(
+    noise(p),
+    noise(p + vec3f(31.416, -47.853, 12.793)),
+    noise(p + vec3f(-233.145, -113.408, -185.31)),
+  );
+}
+
+// the curl of the potential is divergence free, particles swirl without clumping.
+fn curl(p: vec3f) -> vec3f {
+  let e = 0.1;
+  let dx = (potential(p + vec3f(e, 0.0, 0.0)) - potential(p - vec3f(e, 0.0, 0.0))) / (2.0 * e);
+  let dy = (potential(p + vec3f(0.0, e, 0.0)) - potential(p - vec3f(0.0, e, 0.0))) / (2.0 * e);
+  let dz = (potential(p + vec3f(0.0, 0.0, e)) - potential(p - vec3f(0.0, 0.0, e))) / (2.0 * e);
+  return vec3f(dy.z - dz.y, dz.x - dx.z, dx.y - dy.x);
+}
+
+fn update(particle: ptr<function, Particle>, params: Params, sim: SimulationParams) {
+  let p = (*particle).position.xyz * params.frequency + vec3f(sim.time * params.speed);
+  let acceleration = curl(p) * params.strength;
+  set_velocity(particle, get_velocity(*particle) + acceleration * sim.delta_time);
+}
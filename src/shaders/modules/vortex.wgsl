@@ -0,0 +1,25 @@
+#define_import_path vfx::vortex
+
+#import vfx::particle::{Particle, SimulationParams, get_velocity, set_velocity}
+
+struct Params {
+  center: vec3f,
+  strength: f32,
+  axis: vec3f,
+  radius: f32,
+}
+
+fn update(particle: ptr<function, Particle>, params: Params, sim: SimulationParams) {
+  let axis = normalize(params.axis);
+  let offset = (*particle).position.xyz - params.center;
+  // distance to the axis, not to the center.
+  let radial = offset - axis * dot(offset, axis);
+  let distance = length(radial);
+  if (distance >= params.radius || distance < 1e-4) {
+    return;
+  }
+
+  let tangent = cross(axis, radial / distance);
+  let acceleration = tangent * params.strength * (1.0 - distance / params.radius);
+  set_velocity(particle, get_velocity(*particle) + acceleration * sim.delta_time);
+}
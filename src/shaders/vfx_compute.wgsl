@@ -1,68 +1,355 @@
-const PI: f32 = 3.14159265358;
+#import vfx::particle::{
+  Particle, SimulationParams, WORKGROUP_SIZE, dispatch_size, get_velocity, set_velocity, thread_index
+}
+#import vfx::collider::{Collider, DEPTH, collide, colliders, resolve}
+#import vfx::rand::{init_rand, rand_u32, rand, gen_range}
+#ifdef PING_PONG
+#import vfx::grid::particles_src
+#endif
+#ifdef GRID
+#import vfx::grid::{grid_params, neighbors, next_neighbor, neighbor}
+#endif
+#ifdef FLUID
+#import vfx::sph::{FluidParams, poly6, pressure, spiky_gradient, viscosity_laplacian}
+#endif
+
+struct ParticleUniform { // this "represents" an emitter and should be moved.
+  position: vec4f,
+  lifetime: vec2f,
+  spawn_count: u32,
+  seed: u32,
+  color: vec4f, // the color of the origin is kept when alpha is negative
+  direction: vec2f, // range of every component of the direction
+  velocity: vec2f,
+}
 
-var<private> rand_seed : vec2<f32>;
+// salt of the `emit` random streams, they must not repeat the ones of `simulate`.
+const EMIT_STREAM: u32 = 0x9e3779b9u;
 
-fn init_rand(invocation_id : u32, seed : vec4<f32>) {
-  rand_seed = seed.xz;
-  rand_seed = fract(rand_seed * cos(35.456+f32(invocation_id) * seed.yw));
-  rand_seed = fract(rand_seed * cos(41.235+f32(invocation_id) * seed.xw));
+// a list of particle indices, `count` is its length.
+struct ParticleList {
+  count: atomic<u32>,
+  indices: array<u32>,
 }
 
-fn rand() -> f32 {
-  rand_seed.x = fract(cos(dot(rand_seed, vec2<f32>(23.14077926, 232.61690225))) * 136.8168);
-  rand_seed.y = fract(cos(dot(rand_seed, vec2<f32>(54.47856553, 345.84153136))) * 534.7645);
-  return rand_seed.y;
+// `dispatch_workgroups_indirect` arguments followed by the `draw_indirect` ones.
+struct IndirectArgs {
+  workgroups_x: u32,
+  workgroups_y: u32,
+  workgroups_z: u32,
+  vertex_count: u32,
+  instance_count: u32,
+  first_vertex: u32,
+  first_instance: u32,
 }
 
-fn gen_range(min: f32, max: f32) -> f32 {
-  return min + (max - min) * rand();
+
+@binding(0) @group(0) var<storage, read_write> particles_dst : array<Particle>;
+@binding(1) @group(0) var<uniform> sim: SimulationParams;
+@binding(3) @group(0) var<storage, read_write> dead_list: ParticleList;
+@binding(4) @group(0) var<storage, read_write> alive_list: ParticleList; // alive at the start of the frame
+@binding(5) @group(0) var<storage, read_write> next_alive_list: ParticleList; // alive after `simulate`
+@binding(6) @group(0) var<storage, read_write> indirect_args: IndirectArgs;
+@binding(7) @group(0) var<storage, read_write> draw_particles: array<Particle>;
+#ifdef DEPTH_COLLISION
+// `Camera3DUniform` in rust.
+struct Camera {
+  proj: mat4x4f,
+  view: mat4x4f,
+  position: vec3f,
 }
 
+@binding(9) @group(0) var<uniform> camera: Camera;
+// the bits of the depth of the occluders seen by `camera`, drawn by the depth prepass before the
+// step.
+@binding(10) @group(0) var depth_texture: texture_2d<u32>;
+#endif
+#ifdef FLUID
+@binding(14) @group(0) var<uniform> fluid: FluidParams;
+// the density of the particles of `particles_src`, written by `fluid_density` before `simulate`.
+@binding(15) @group(0) var<storage, read_write> densities: array<f32>;
+#endif
+@binding(0) @group(1) var<uniform> particle_uniform: ParticleUniform;
 
-struct SimulationParams {
-    dir: vec2f,
+fn is_dead(particle: Particle) -> bool {
+  return particle.lifetime >= 0.0 && particle.age >= particle.lifetime;
 }
 
-struct Uniforms {
-  delta_time: f32,
+// returns the slot of a particle that just died to the dead list.
+fn kill(idx: u32, particle: Particle) {
+  let dead_count = atomicAdd(&dead_list.count, 1u);
+  dead_list.indices[dead_count] = idx;
+  particles_dst[idx] = particle;
 }
 
+#ifdef DEPTH_COLLISION
+// distance along the view axis of a point of the depth prepass, from its perspective depth. It
+// inverts `depth = clip.z / clip.w` for any projection, its `w` is not always the distance.
+fn view_distance(depth: f32) -> f32 {
+  let p = camera.proj;
+  return (p[3][2] - depth * p[3][3]) / (p[2][2] - depth * p[2][3]);
+}
 
-struct ParticleUniform { // this "represents" an emitter and should be moved.
-  position: vec4f,
-  velocity: f32,
+// the `w` of the clip position of a point at the distance `d` along the view axis.
+fn clip_w(d: f32) -> f32 {
+  return camera.proj[3][3] - d * camera.proj[2][3];
 }
 
-struct Particle {
-  position: vec4f,
-  color: vec4f,
-  dir: vec3f,
-  velocity: f32,
-  origin: vec3f,
+// view space position of the surface drawn at `texel`.
+fn view_position(texel: vec2u, size: vec2u) -> vec3f {
+  let uv = (vec2f(texel) + 0.5) / vec2f(size);
+  let ndc = vec2f(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+  let d = view_distance(bitcast<f32>(textureLoad(depth_texture, texel, 0).r));
+  let w = clip_w(d);
+  return vec3f(ndc.x * w / camera.proj[0][0], ndc.y * w / camera.proj[1][1], -d);
 }
 
+// bounces a particle on the surface of the depth prepass, it's inside when it's behind the
+// surface by less than the thickness. Returns true when the particle must die instead.
+fn collide_depth(particle: ptr<function, Particle>, collider: Collider) -> bool {
+  let position = (*particle).position.xyz;
+  let clip = camera.proj * camera.view * vec4f(position, 1.0);
+  if (clip.w <= 0.0) {
+    return false;
+  }
+  let ndc = clip.xyz / clip.w;
+  if (any(abs(ndc.xy) >= vec2f(1.0))) {
+    return false;
+  }
 
-@binding(0) @group(0) var<storage, read_write> particles_dst : array<Particle>;
-@binding(1) @group(0) var<storage> sim_params_groups: SimulationParams;
-@binding(2) @group(0) var<uniform> uniforms: Uniforms;
-@binding(0) @group(1) var<uniform> particle_uniform: ParticleUniform;
+  let size = textureDimensions(depth_texture);
+  let uv = vec2f(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+  let texel = min(vec2u(uv * vec2f(size)), size - 1u);
+  let depth = bitcast<f32>(textureLoad(depth_texture, texel, 0).r);
+  // nothing was drawn where the depth is still cleared to 1.
+  if (depth >= 1.0) {
+    return false;
+  }
+  let surface = view_distance(depth);
+  let distance = -(camera.view * vec4f(position, 1.0)).z;
+  if (distance < surface || distance > surface + collider.size.x) {
+    return false;
+  }
+  if (collider.kill != 0u) {
+    return true;
+  }
+
+  // the normal of the surface from the next texels, the last row and column use the previous ones.
+  let center = view_position(texel, size);
+  let x = select(texel.x + 1u, texel.x - 1u, texel.x + 1u >= size.x);
+  let y = select(texel.y + 1u, texel.y - 1u, texel.y + 1u >= size.y);
+  let dx = view_position(vec2u(x, texel.y), size) - center;
+  let dy = view_position(vec2u(texel.x, y), size) - center;
+  var n = cross(dx, dy);
+  if (dot(n, n) == 0.0) {
+    n = -center;
+  }
+  // towards the camera, it's the origin of the view space.
+  n = normalize(select(n, -n, dot(n, center) > 0.0));
+  // the inverse of the rotation of the view.
+  let normal = n * mat3x3f(camera.view[0].xyz, camera.view[1].xyz, camera.view[2].xyz);
+
+  // back on the surface along the ray from the camera.
+  let on_surface = camera.position + (position - camera.position) * (surface / distance);
+  resolve(particle, on_surface - position, normal, collider);
+  return false;
+}
+#endif
+
+#ifdef FLUID
+// the acceleration of a particle from the pressure, the viscosity and the surface tension of its
+// neighbors, the smoothing radius is the cell size of the grid.
+fn fluid_acceleration(idx: u32, particle: Particle) -> vec3f {
+  let h = grid_params.cell_size;
+  let position = particle.position.xyz;
+  let velocity = get_velocity(particle);
+  let density = densities[idx];
+  let p = pressure(density, fluid);
+
+  var pressure_force = vec3f(0.0);
+  var viscosity_force = vec3f(0.0);
+  var cohesion = vec3f(0.0);
+  var it = neighbors(position);
+  while (next_neighbor(&it)) {
+    if (it.index == idx) {
+      continue;
+    }
+    let other = neighbor(it);
+    let r = position - other.position.xyz;
+    let d = length(r);
+    if (d >= h) {
+      continue;
+    }
+    let other_density = densities[it.index];
+    let other_p = pressure(other_density, fluid);
+    pressure_force -= fluid.mass * (p + other_p) / (2.0 * other_density) * spiky_gradient(r, d, h);
+    viscosity_force += fluid.mass * (get_velocity(other) - velocity) / other_density
+      * viscosity_laplacian(d, h);
+    cohesion -= fluid.mass * poly6(d * d, h) * r;
+  }
+  return (pressure_force + fluid.viscosity * viscosity_force) / density
+    + fluid.surface_tension * cohesion;
+}
+
+// the density of every live particle of `particles_src`, before `simulate` reads them.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn fluid_density(
+  @builtin(global_invocation_id) global_invocation_id : vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+    let thread = thread_index(global_invocation_id, num_workgroups);
+    if (thread >= atomicLoad(&alive_list.count)) {
+      return;
+    }
+
+    let idx = alive_list.indices[thread];
+    let position = particles_src[idx].position.xyz;
+    var density = 0.0;
+    var it = neighbors(position);
+    while (next_neighbor(&it)) {
+      let r = position - neighbor(it).position.xyz;
+      density += fluid.mass * poly6(dot(r, r), grid_params.cell_size);
+    }
+    densities[idx] = density;
+}
+#endif
+
+// recycles `spawn_count` slots from the dead list and appends them to the alive list.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn emit(
+  @builtin(global_invocation_id) global_invocation_id : vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+    if (thread_index(global_invocation_id, num_workgroups) >= particle_uniform.spawn_count) {
+      return;
+    }
 
-@compute @workgroup_size(64)
-fn simulate(@builtin(global_invocation_id) global_invocation_id : vec3u) {
-    let total = arrayLength(&particles_dst);
-  
-    let idx = global_invocation_id.x;
-  
-    if (idx >= total) {
+    let dead_count = atomicSub(&dead_list.count, 1u);
+    if (dead_count == 0u || dead_count > arrayLength(&dead_list.indices)) {
+      // the list was already empty (or it wrapped around), give the slot back.
+      atomicAdd(&dead_list.count, 1u);
       return;
     }
-    
+
+    let idx = dead_list.indices[dead_count - 1u];
     var particle: Particle = particles_dst[idx];
-    init_rand(idx, vec4f(particle.position.x, particle.position.y, particle.position.z, uniforms.delta_time));
+    init_rand(idx, sim.frame, particle_uniform.seed ^ EMIT_STREAM);
+
+    particle.position = vec4f(particle.origin, particle.position.w);
+    particle.prev_position = particle.origin;
+    particle.age = 0.0;
+    particle.lifetime = gen_range(particle_uniform.lifetime.x, particle_uniform.lifetime.y);
+    // the slot still holds the particle that died in it.
+    let direction = particle_uniform.direction;
+    particle.dir = vec3f(
+      gen_range(direction.x, direction.y),
+      gen_range(direction.x, direction.y),
+      gen_range(direction.x, direction.y),
+    );
+    particle.velocity = gen_range(particle_uniform.velocity.x, particle_uniform.velocity.y);
+    particle.color = select(
+      unpack4x8unorm(particle.origin_color),
+      particle_uniform.color,
+      particle_uniform.color.a >= 0.0,
+    );
+
+    particles_dst[idx] = particle;
+
+#ifdef PING_PONG
+    // `emit` runs after `simulate`, the particle is drawn where it spawns and moves from the next
+    // step.
+    let alive_count = atomicAdd(&next_alive_list.count, 1u);
+    next_alive_list.indices[alive_count] = idx;
+    draw_particles[alive_count] = particle;
+#else
+    let alive_count = atomicAdd(&alive_list.count, 1u);
+    alive_list.indices[alive_count] = idx;
+#endif
+}
+
+// sizes the `simulate` dispatch from the alive list, it runs on a single thread.
+@compute @workgroup_size(1)
+fn prepare_simulate() {
+    let alive_count = atomicLoad(&alive_list.count);
+    let workgroups = dispatch_size(alive_count);
+    indirect_args.workgroups_x = workgroups.x;
+    indirect_args.workgroups_y = workgroups.y;
+    indirect_args.workgroups_z = workgroups.z;
+    atomicStore(&next_alive_list.count, 0u);
+}
+
+// sizes the draw call from the particles that survived `simulate`.
+@compute @workgroup_size(1)
+fn prepare_draw() {
+    indirect_args.vertex_count = 6u;
+    indirect_args.instance_count = atomicLoad(&next_alive_list.count);
+    indirect_args.first_vertex = 0u;
+    indirect_args.first_instance = 0u;
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn simulate(
+  @builtin(global_invocation_id) global_invocation_id : vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+    let thread = thread_index(global_invocation_id, num_workgroups);
+    if (thread >= atomicLoad(&alive_list.count)) {
+      return;
+    }
+
+    let idx = alive_list.indices[thread];
+#ifdef PING_PONG
+    var particle: Particle = particles_src[idx];
+#else
+    var particle: Particle = particles_dst[idx];
+#endif
+
+    particle.age += sim.delta_time;
+    if (is_dead(particle)) {
+      kill(idx, particle);
+      return;
+    }
+
+    init_rand(idx, sim.frame, particle_uniform.seed);
+    particle.prev_position = particle.position.xyz;
+    set_velocity(&particle, get_velocity(particle) + sim.gravity * sim.delta_time);
+#ifdef FLUID
+    let acceleration = fluid_acceleration(idx, particles_src[idx]);
+    set_velocity(&particle, get_velocity(particle) + acceleration * sim.delta_time);
+#endif
 
-    
-    
     ;;COMPUTE_CODE
 
+    particle.position += vec4f(get_velocity(particle) * sim.delta_time, 0.0);
+
+    for (var i = 0u; i < colliders.count; i++) {
+      let collider = colliders.colliders[i];
+      var killed = false;
+      if (collider.kind == DEPTH) {
+#ifdef DEPTH_COLLISION
+        killed = collide_depth(&particle, collider);
+#endif
+      } else {
+        killed = collide(&particle, collider);
+      }
+      if (killed) {
+        particle.lifetime = particle.age;
+        kill(idx, particle);
+        return;
+      }
+    }
+
+    let position = particle.position.xyz;
+    if (any(position < sim.bounds_min) || any(position > sim.bounds_max)) {
+      particle.lifetime = particle.age;
+      kill(idx, particle);
+      return;
+    }
+
     particles_dst[idx] = particle;
-}
\ No newline at end of file
+
+    // survivors are packed at the front of `draw_particles`, which is the instance buffer.
+    let alive_count = atomicAdd(&next_alive_list.count, 1u);
+    next_alive_list.indices[alive_count] = idx;
+    draw_particles[alive_count] = particle;
+}
@@ -8,11 +8,21 @@ struct Camera {
 @group(0) @binding(0)
 var<uniform> camera: Camera;
 
+struct RenderParams {
+    particle_size: f32,
+    alpha: f32, // interpolation between the last two simulation steps
+}
+
+@group(1) @binding(0)
+var<uniform> render_params: RenderParams;
+
 struct In {
     @location(0) vertex_position: vec2f,
     @location(1) position: vec4f,
     @location(2) color: vec4f,
-    
+    @location(3) prev_position: vec3f,
+    @location(4) age: f32,
+    @location(5) lifetime: f32,
 }
 
 struct Out {
@@ -31,9 +41,10 @@ fn vs_main(in: In) -> Out {
     let right = vec3<f32>(view[0][0], view[1][0], view[2][0]); // right
     let up = vec3<f32>(view[0][1], view[1][1], view[2][1]); // up
 
-    let worldPosition = in.position.xyz
-                        + right * (in.vertex_position.x * 0.1)
-                        + up * (in.vertex_position.y * 0.1);
+    let position = mix(in.prev_position, in.position.xyz, render_params.alpha);
+    let worldPosition = position
+                        + right * (in.vertex_position.x * render_params.particle_size)
+                        + up * (in.vertex_position.y * render_params.particle_size);
     
 
     out.clip_position = proj_view * vec4<f32>(worldPosition, 1.0);
@@ -51,4 +62,14 @@ fn fs_main(in: Out) -> @location(0) vec4f {
     var color = in.color;
     color.a *= alpha;
     return color;
-}
\ No newline at end of file
+}
+
+// the depth prepass, the particles of the occluders are opaque discs. The bits of their depth are
+// written to the color target, the compute shaders can't load the depth attachment on GL.
+@fragment
+fn fs_depth(in: Out) -> @location(0) u32 {
+    if (length(in.vertex_position) > 0.5) {
+        discard;
+    }
+    return bitcast<u32>(in.clip_position.z);
+}
@@ -0,0 +1,171 @@
+//! The fluid of an emitter as the `simulate` kernel reads it, and the CPU reference of the
+//! `vfx::sph` wgsl module and of the fluid passes of the compute shader: the same particles give
+//! the densities of `fluid_density` and the accelerations `simulate` adds to the velocities. The
+//! neighbors are every other particle closer than the smoothing radius instead of the ones of the
+//! grid.
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::emitter::FluidSettings;
+
+/// A [`FluidSettings`] laid out like the wgsl `FluidParams`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FluidParams {
+    pub rest_density: f32,
+    pub stiffness: f32,
+    pub viscosity: f32,
+    pub surface_tension: f32,
+    pub mass: f32,
+    _pad: [u32; 3],
+}
+
+impl FluidParams {
+    pub fn new(settings: &FluidSettings) -> Self {
+        Self {
+            rest_density: settings.rest_density,
+            stiffness: settings.stiffness,
+            viscosity: settings.viscosity,
+            surface_tension: settings.surface_tension,
+            mass: settings.mass,
+            _pad: [0; 3],
+        }
+    }
+}
+
+/// Same as `poly6(r2, h)`.
+pub fn poly6(r2: f32, h: f32) -> f32 {
+    let h2 = h * h;
+    if r2 >= h2 {
+        return 0.0;
+    }
+    let d = h2 - r2;
+    315.0 / (64.0 * std::f32::consts::PI * h.powi(9)) * d * d * d
+}
+
+/// Same as `spiky_gradient(r, d, h)`.
+pub fn spiky_gradient(r: Vector3<f32>, d: f32, h: f32) -> Vector3<f32> {
+    if d >= h || d <= 0.0 {
+        return Vector3::new(0.0, 0.0, 0.0);
+    }
+    let x = h - d;
+    r * (-45.0 / (std::f32::consts::PI * h.powi(6)) * x * x / d)
+}
+
+/// Same as `viscosity_laplacian(d, h)`.
+pub fn viscosity_laplacian(d: f32, h: f32) -> f32 {
+    if d >= h {
+        return 0.0;
+    }
+    45.0 / (std::f32::consts::PI * h.powi(6)) * (h - d)
+}
+
+/// Same as `pressure(density, params)`.
+pub fn pressure(density: f32, params: &FluidParams) -> f32 {
+    params.stiffness * (density - params.rest_density).max(0.0)
+}
+
+/// The densities written by `fluid_density`, `h` is the cell size of the grid.
+pub fn densities(positions: &[Vector3<f32>], params: &FluidParams, h: f32) -> Vec<f32> {
+    positions
+        .iter()
+        .map(|&position| {
+            positions
+                .iter()
+                .map(|&other| params.mass * poly6((position - other).magnitude2(), h))
+                .sum()
+        })
+        .collect()
+}
+
+/// The accelerations of `fluid_acceleration` from the `densities` of the same particles.
+pub fn accelerations(
+    positions: &[Vector3<f32>],
+    velocities: &[Vector3<f32>],
+    densities: &[f32],
+    params: &FluidParams,
+    h: f32,
+) -> Vec<Vector3<f32>> {
+    let zero = Vector3::new(0.0, 0.0, 0.0);
+    (0..positions.len())
+        .map(|i| {
+            let p = pressure(densities[i], params);
+            let mut pressure_force = zero;
+            let mut viscosity_force = zero;
+            let mut cohesion = zero;
+            for j in 0..positions.len() {
+                let r = positions[i] - positions[j];
+                let d = r.magnitude();
+                if i == j || d >= h {
+                    continue;
+                }
+                let other_p = pressure(densities[j], params);
+                pressure_force -=
+                    spiky_gradient(r, d, h) * (params.mass * (p + other_p) / (2.0 * densities[j]));
+                viscosity_force += (velocities[j] - velocities[i])
+                    * (params.mass / densities[j] * viscosity_laplacian(d, h));
+                cohesion -= r * (params.mass * poly6(d * d, h));
+            }
+            (pressure_force + viscosity_force * params.viscosity) / densities[i]
+                + cohesion * params.surface_tension
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const H: f32 = 1.5;
+
+    /// `∫ kernel(r) 4πr² dr` over the support.
+    fn integrate(kernel: impl Fn(f32) -> f32) -> f32 {
+        let steps = 10000;
+        let dr = H / steps as f32;
+        (0..steps)
+            .map(|i| {
+                let r = (i as f32 + 0.5) * dr;
+                kernel(r) * 4.0 * std::f32::consts::PI * r * r * dr
+            })
+            .sum()
+    }
+
+    /// the spiky kernel of which `spiky_gradient` is the gradient.
+    fn spiky(r: f32) -> f32 {
+        15.0 / (std::f32::consts::PI * H.powi(6)) * (H - r).max(0.0).powi(3)
+    }
+
+    #[test]
+    fn poly6_is_normalized() {
+        assert!((integrate(|r| poly6(r * r, H)) - 1.0).abs() < 1e-3);
+        assert_eq!(poly6(H * H, H), 0.0);
+        assert_eq!(poly6(4.0 * H * H, H), 0.0);
+    }
+
+    #[test]
+    fn spiky_gradient_is_the_gradient_of_a_normalized_kernel() {
+        assert!((integrate(spiky) - 1.0).abs() < 1e-3);
+
+        let dir = Vector3::new(1.0, 2.0, -2.0).normalize();
+        for d in [0.1, 0.5, 1.0, 1.4] {
+            let e = 1e-3;
+            let slope = (spiky(d + e) - spiky(d - e)) / (2.0 * e);
+            let gradient = spiky_gradient(dir * d, d, H);
+            assert!((gradient - dir * slope).magnitude() < 1e-3 * slope.abs().max(1.0));
+        }
+        assert_eq!(spiky_gradient(dir * H, H, H), Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            spiky_gradient(dir * 0.0, 0.0, H),
+            Vector3::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn viscosity_laplacian_fades_to_zero() {
+        let center = 45.0 / (std::f32::consts::PI * H.powi(5));
+        assert!((viscosity_laplacian(0.0, H) - center).abs() < 1e-5 * center);
+        assert!(viscosity_laplacian(H * 0.99, H) > 0.0);
+        assert_eq!(viscosity_laplacian(H, H), 0.0);
+        assert_eq!(viscosity_laplacian(2.0 * H, H), 0.0);
+    }
+}
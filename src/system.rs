@@ -1,178 +1,77 @@
-use core::f32;
-use std::borrow::Cow;
 use std::path::Path;
 
-use crate::attr::{AttrContext, ShaderBuilder};
+use crate::emitter::{
+    BlendMode, Bounds, DescError, Emitter, FieldError, SceneDesc, SimulationSettings,
+};
+use crate::particle::Particle;
+use crate::pcg::Pcg;
 use crate::postproc::Bloom;
 use crate::profiler::Profiler;
 use crate::texture::{create_bind_group_texture_layout, Texture};
+use crate::timestep::{FixedTimestep, MAX_SUBSTEPS};
 use crate::window::InputEvent;
 use crate::{
     camera::{Camera2D, Camera2DUniform, Camera3D, Camera3DUniform, CameraController},
     quad::{Quad, VERTICES},
     uniform::Uniform,
 };
-use crate::{profiler, texture, uniform};
+use crate::{profiler, texture};
+
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
 
-use cgmath::{InnerSpace, Vector3};
 use naga_oil::compose::{ComposableModuleDescriptor, Composer, NagaModuleDescriptor};
-use rand::Rng;
 use wgpu::util::DeviceExt;
 use wgpu::QuerySet;
 
-#[cfg(target_arch = "wasm32")]
-const PARTICLE_POOLING: u64 = 250_000;
-
-// #[cfg(not(target_arch = "wasm32"))]
-// const PARTICLE_POOLING: u64 = 1_000_000;
-
-//*  4_194_240 / 64 = 65535 MAX (x) DISPATCHES
-// #[cfg(not(target_arch = "wasm32"))]
-// const PARTICLE_POOLING: u64 = 4_194_241;
-
-fn dv() -> Vector3<f32> {
-    let mut rng = rand::thread_rng();
-
-    let theta = rng.gen_range(0.0..2.0 * f32::consts::PI);
-    let phi = rng.gen_range(0.0..f32::consts::PI);
-
-    let x = phi.sin() * theta.cos();
-    let y = phi.sin() * theta.sin();
-    let z = phi.cos();
-
-    cgmath::Vector3::new(x, y, z).normalize()
-}
-
-use image::{GenericImageView, RgbaImage};
-
-const PARTICLE_POOLING: usize = 1000 * 937;
-
-fn generate_particles() -> Vec<f32> {
-    let img = include_bytes!("./hemis.jpg");
-    let img = image::load_from_memory(img)
-        .expect("Failed to load image")
-        .to_rgba8();
-
-    let d = img.dimensions();
-    let pp = d.0 * d.1;
-    println!("{:?} {:?}", pp, d);
-    let mut particles = vec![0.0f32; 16 * pp as usize];
-
-    for (i, chunk) in particles.chunks_mut(16).enumerate() {
-        let mut rng = rand::thread_rng();
-
-        // Compute texture coordinates
-        let px = i % d.0 as usize;
-        let py = i / d.0 as usize;
-        // Sample color from image
-
-        let pixel = img.get_pixel(px as u32, py as u32);
-        let r = pixel[0] as f32 / 255.0;
-        let g = pixel[1] as f32 / 255.0;
-        let b = pixel[2] as f32 / 255.0;
-        let a = pixel[3] as f32 / 255.0;
-
-        // Map to world space (-1 to 1)
-        let world_x = (px as f32 / d.0 as f32) * 2.0 - 1.0;
-        let world_y = (py as f32 / d.1 as f32) * 2.0 - 1.0;
-
-        // Position
-        chunk[0] = -world_x * 30.0;
-        chunk[1] = -world_y * 30.0;
-        chunk[2] = 0.0;
-        chunk[3] = 0.0;
-
-        // Color
-        chunk[4] = r;
-        chunk[5] = g;
-        chunk[6] = b;
-        chunk[7] = a;
-
-        // Direction
-        chunk[8] = rng.gen_range(0.0..0.1);
-        chunk[9] = rng.gen_range(0.0..0.1);
-        chunk[10] = rng.gen_range(0.0..0.1);
-
-        // Velocity
-        chunk[11] = rng.gen_range(-0.1..0.1);
-
-        // Origin
-        chunk[12] = -world_x * 30.0;
-        chunk[13] = -world_y * 30.0;
-        chunk[14] = 0.0;
-    }
-
-    particles
-}
-
 pub struct System {
     view: texture::Texture,
 
     camera: Camera3D,
     camera_controller: CameraController,
-    pipeline: wgpu::RenderPipeline,
     blend_pipeline: wgpu::RenderPipeline,
-    compute_pipeline: wgpu::ComputePipeline,
+    /// draws the scene alone when the bloom is disabled.
+    copy_pipeline: wgpu::RenderPipeline,
 
     vertex_buffer: wgpu::Buffer,
+    /// the [`SimulationParams`] of the step, bound at **@binding(1)** of every emitter.
     simulation_buffer: wgpu::Buffer,
-    particle_buffer: wgpu::Buffer,
-    uniform_buffer: wgpu::Buffer,
-    pub particle_uniform: uniform::Uniform<AttrContext>,
-    /**test */
-    /// contains all the data to compute the paricles. \
-    /// holds the *particles buffer* at **@binding(0)** \
-    /// holds the *simulation params buffer* at **@binding(1)** \
-    /// holds the *delta time buffer* at **@binding(2)**
-    bind_group: wgpu::BindGroup,
+    /// the depth of the occluders, drawn before the steps when an emitter has a `Depth` collider.
+    /// It's the color target of the prepass, depth textures can't be loaded from on GL and
+    /// `R32Float` is not renderable there, so it holds the bits of the depth.
+    depth_view: wgpu::TextureView,
+    /// the depth attachment of the prepass, it keeps the nearest occluder in `depth_view`.
+    depth_attachment: wgpu::TextureView,
+    pub emitters: Vec<Emitter>,
     bloom: Bloom,
     time: f64,
+    frame: u32,
+    /// simulates in fixed steps when set, otherwise every frame is a single step of `dt`.
+    pub timestep: Option<FixedTimestep>,
+    paused: bool,
+    /// a single step requested while paused, see [`System::step_once`].
+    step_requested: bool,
+    time_scale: f32,
+    /// written to the [`SimulationParams`] of every step.
+    pub simulation: SimulationSettings,
+    seed: u32,
+    /// the params of every step simulated in the frame.
+    steps: Vec<SimulationParams>,
+    /// `steps` staged on the gpu, each step copies its entry to `simulation_buffer`.
+    steps_buffer: wgpu::Buffer,
 }
 
 impl System {
-    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+    /// Fails with every emitter of `desc` that can't be built, see [`Emitter::new`].
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        size: (u32, u32),
+        desc: &SceneDesc,
+    ) -> Result<Self, DescError> {
         let mut camera = Camera3D::new(Uniform::<Camera3DUniform>::new(&device));
 
-        let module = ShaderBuilder::build_module(&include_str!("shaders/vfx_render.wgsl"));
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
-            label: Some("vfx_render.wgsl"),
-        });
-
-        let module =
-            ShaderBuilder::build_module(&include_str!("shaders/vfx_compute.wgsl").replace(
-                ";;COMPUTE_CODE",
-                "
-
-     if (particle_uniform.velocity >= 0.0) {
-        let d = normalize(vec3f(gen_range(-1.0, 1.0), gen_range(-1.0, 1.0), gen_range(-1.0, 1.0))); // Random direction
-        let force = clamp(1.0 / (length(d) + 1.0), 0.1, 5.0); // Avoid division by zero
-
-        particle.dir = d * force; // Move outward in a random direction
-    } else {
-        let ndir = normalize(particle.origin - particle.position.xyz);
-        let force = length(particle.origin - particle.position.xyz) * 0.05; // Scale force based on distance
-        particle.dir = ndir * force;
-    }
-
-particle.velocity = abs(particle_uniform.velocity) * 2.0; 
-
-particle.position.x += particle.velocity * particle.dir.x * uniforms.delta_time;
-particle.position.y += particle.velocity * particle.dir.y * uniforms.delta_time;
-particle.position.z += particle.velocity * particle.dir.z * uniforms.delta_time;
-                ",
-            ));
-
-        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
-            label: Some("vfx_compute.wgsl"),
-        });
-
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Main_pipeline_layout"),
-            bind_group_layouts: &[&camera.uniform.bind_group_layout],
-            push_constant_ranges: &[],
-        });
         camera.build_view_projection_matrix();
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -180,82 +79,74 @@ particle.position.z += particle.velocity * particle.dir.z * uniforms.delta_time;
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
 
-        // compute
-        let particle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Particle Buffer"),
-            contents: bytemuck::cast_slice(&generate_particles()),
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST,
-        });
-
-        // let particle_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        //     label: Some(&format!("Particle Buffer")),
-        //     size: 8 * 4 * PARTICLE_POOLING,
-        //     usage: wgpu::BufferUsages::VERTEX
-        //         | wgpu::BufferUsages::STORAGE
-        //         | wgpu::BufferUsages::COPY_DST,
-        //     mapped_at_creation: false,
-        // });
-
         let simulation_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Compute Buffer"),
-            contents: bytemuck::bytes_of(&[0.0, 0.0]), //dummy data
-            usage: wgpu::BufferUsages::UNIFORM
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
+            label: Some("Simulation Params Buffer"),
+            contents: bytemuck::bytes_of(&SimulationParams::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
-        // for testing (pos.xyz, mass)
 
-        let mut uniform_bytes: Vec<f32> = vec![0.0];
+        let prepass_texture = |label, format| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size.0,
+                        height: size.1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let depth_view = prepass_texture("Depth Prepass Texture", DEPTH_COPY_FORMAT);
+        let depth_attachment = prepass_texture("Depth Prepass Attachment", DEPTH_FORMAT);
+
+        let scene = SceneBindings {
+            simulation: &simulation_buffer,
+            camera: &camera.uniform.buffer,
+            depth: &depth_view,
+        };
+        let mut emitters = vec![];
+        let mut errors = vec![];
+        for (i, emitter) in desc.emitters.iter().enumerate() {
+            match Emitter::new(
+                device,
+                queue,
+                format,
+                emitter,
+                Pcg::new(i as u32, 0, desc.seed).next_u32(),
+                &camera.uniform.bind_group_layout,
+                &scene,
+            ) {
+                Ok(emitter) => emitters.push(emitter),
+                Err(error) => errors.push(error.prefixed(&format!("emitters[{i}]."))),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(DescError::Invalid(errors));
+        }
 
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Compute Buffer"),
-            contents: bytemuck::cast_slice(&uniform_bytes),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        let bloom = Bloom::new(device, format, size, &desc.bloom);
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &create_compute_bind_group_layout(device),
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: particle_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: simulation_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: uniform_buffer.as_entire_binding(),
-                },
-            ],
-            label: None,
+        let steps_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Simulation Steps Buffer"),
+            size: (MAX_SUBSTEPS as usize * size_of::<SimulationParams>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
         });
-
-        let pipeline = create_render_pipeline(device, &shader, config.format, &pipeline_layout);
-        let particle_uniform = Uniform::<AttrContext>::new(&device);
-        let compute_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Compute Pipeline Layout"),
-                bind_group_layouts: &[
-                    &create_compute_bind_group_layout(device),
-                    &particle_uniform.bind_group_layout,
-                ],
-                push_constant_ranges: &[],
-            });
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Particles compute pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            module: &compute_shader,
-            entry_point: Some("simulate"),
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-            cache: None,
+        let timestep = desc.timestep.as_ref().map(|timestep| {
+            FixedTimestep::new(
+                instant::Duration::from_secs_f32(timestep.step),
+                timestep.max_substeps,
+            )
         });
 
-        let bloom = Bloom::new(device, config.format, (config.width, config.height));
-
         let bind_group_texture_layout = create_bind_group_texture_layout(device);
 
         let blend_pipeline_layout =
@@ -267,73 +158,167 @@ particle.position.z += particle.velocity * particle.dir.z * uniforms.delta_time;
         let shader_blend = device.create_shader_module(wgpu::include_wgsl!("./shaders/blend.wgsl"));
         let shader_fullscreen_quad = device
             .create_shader_module(wgpu::include_wgsl!("./shaders/fullscreen_quad_vertex.wgsl"));
-        let blend_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Final pipeline"),
-            layout: Some(&blend_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader_fullscreen_quad,
-                entry_point: Some("vs_main"),
-                compilation_options: Default::default(),
-                buffers: &[],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader_blend,
-                entry_point: Some("fs_main"),
-                compilation_options: Default::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::all(),
-                })],
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
-        Self {
-            view: Texture::empty(
-                device,
-                (config.width, config.height),
-                Some("System texture view"),
-            )
-            .expect("Failed to build texture"),
+        let final_pipeline = |entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Final pipeline"),
+                layout: Some(&blend_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader_fullscreen_quad,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_blend,
+                    entry_point: Some(entry_point),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::all(),
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let blend_pipeline = final_pipeline("fs_main");
+        let copy_pipeline = final_pipeline("fs_copy");
+        Ok(Self {
+            view: Texture::empty(device, size, Some("System texture view"))
+                .expect("Failed to build texture"),
             camera,
             camera_controller: CameraController::new(2.0),
-            bind_group,
-            particle_buffer,
             simulation_buffer,
-            uniform_buffer,
-            particle_uniform,
+            depth_view,
+            depth_attachment,
+            emitters,
             bloom,
             // camera_pos_uniform: Uniform::<f32>::new(&device),
             vertex_buffer,
-            pipeline,
-            compute_pipeline,
             blend_pipeline,
+            copy_pipeline,
             time: 0.0,
-        }
+            frame: 0,
+            timestep,
+            paused: false,
+            step_requested: false,
+            time_scale: 1.0,
+            simulation: desc.simulation.clone(),
+            seed: desc.seed,
+            steps: vec![],
+            steps_buffer,
+        })
     }
 
     pub fn input(&mut self, event: InputEvent) -> bool {
+        if let InputEvent::Window(WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(keycode),
+                    repeat: false,
+                    ..
+                },
+            ..
+        }) = event
+        {
+            match keycode {
+                KeyCode::Space => {
+                    self.set_paused(!self.paused);
+                    return true;
+                }
+                KeyCode::Period => {
+                    self.step_once();
+                    return true;
+                }
+                KeyCode::Minus => {
+                    self.set_time_scale(self.time_scale / 2.0);
+                    return true;
+                }
+                KeyCode::Equal => {
+                    self.set_time_scale(self.time_scale * 2.0);
+                    return true;
+                }
+                _ => {}
+            }
+        }
         self.camera_controller.process_events(event)
     }
 
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Freezes the simulation, the camera still moves and the particles are still drawn.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.step_requested = false;
+    }
+
+    /// Simulates a single step on the next update, it pauses the simulation if it was running.
+    pub fn step_once(&mut self) {
+        self.paused = true;
+        self.step_requested = true;
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Multiplies the frame time, values below 1 give slow motion.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    }
+
     pub fn update(&mut self, queue: &wgpu::Queue, dt: instant::Duration) {
-        self.time += dt.as_secs_f64();
         self.camera_controller.update_camera(&mut self.camera);
         self.camera.build_view_projection_matrix();
         self.camera.update((0.0, 0.0, 0.0).into());
 
-        let uniform_bytes: Vec<f32> = vec![dt.as_secs_f32()];
+        let (steps, dt, alpha) = match &mut self.timestep {
+            // a paused simulation is drawn exactly as it is, without interpolation.
+            _ if self.paused => {
+                let step = self.timestep.as_ref().map_or(SINGLE_STEP, |t| t.step);
+                (std::mem::take(&mut self.step_requested) as u32, step, 1.0)
+            }
+            Some(timestep) => {
+                let dt = dt.mul_f32(self.time_scale);
+                (timestep.advance(dt), timestep.step, timestep.alpha())
+            }
+            None => (1, dt.mul_f32(self.time_scale), 1.0),
+        };
+
+        let (bounds_min, bounds_max) = match self.simulation.bounds {
+            Some(Bounds { min, max }) => (min, max),
+            None => ([f32::NEG_INFINITY; 3], [f32::INFINITY; 3]),
+        };
+        self.steps.clear();
+        for _ in 0..steps {
+            self.time += dt.as_secs_f64();
+            self.frame += 1;
+            self.steps.push(SimulationParams {
+                delta_time: dt.as_secs_f32(),
+                time: self.time as f32,
+                frame: self.frame,
+                seed: self.seed,
+                gravity: self.simulation.gravity,
+                wind: self.simulation.wind,
+                bounds_min,
+                bounds_max,
+                ..Default::default()
+            });
+        }
 
-        queue.write_buffer(
-            &self.uniform_buffer,
-            0,
-            bytemuck::cast_slice(&uniform_bytes),
-        );
-        self.particle_uniform.write(queue);
+        queue.write_buffer(&self.steps_buffer, 0, bytemuck::cast_slice(&self.steps));
+        for emitter in &mut self.emitters {
+            emitter.update(steps, dt.as_secs_f32());
+            emitter.write(queue, alpha);
+        }
+        self.bloom.uniform.write(queue);
 
         self.camera.uniform.write(queue);
     }
@@ -344,19 +329,77 @@ particle.position.z += particle.velocity * particle.dir.z * uniforms.delta_time;
         ctx_view: &wgpu::TextureView,
         profiler: &Profiler,
     ) {
-        {
+        // the steps of the frame collide with the occluders of the last one, seen by the camera
+        // of this frame.
+        if self.emitters.iter().any(Emitter::depth_collision) {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Depth Prepass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.depth_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // the bits of a depth of 1.
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 1f32.to_bits() as f64,
+                            g: 0.0,
+                            b: 0.0,
+                            a: 0.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_attachment,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            for emitter in &self.emitters {
+                emitter.draw_depth(
+                    &mut rpass,
+                    &self.camera.uniform.bind_group,
+                    &self.vertex_buffer,
+                );
+            }
+        }
+
+        // a frame without steps still runs an empty pass so the compute timestamps are written.
+        let steps = self.steps.len();
+        for step in 0..steps.max(1) {
+            if step < steps {
+                let size = size_of::<SimulationParams>() as wgpu::BufferAddress;
+                encoder.copy_buffer_to_buffer(
+                    &self.steps_buffer,
+                    step as wgpu::BufferAddress * size,
+                    &self.simulation_buffer,
+                    0,
+                    size,
+                );
+                for emitter in &self.emitters {
+                    emitter.prepare_step(encoder, step);
+                }
+            }
+
             let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
                 label: None,
-                timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
-                    query_set: &profiler.timestamps.get(1).unwrap().query_timing,
-                    beginning_of_pass_write_index: Some(0),
-                    end_of_pass_write_index: Some(1),
+                timestamp_writes: profiler.timestamps.get(1).map(|query| {
+                    wgpu::ComputePassTimestampWrites {
+                        query_set: &query.query_timing,
+                        beginning_of_pass_write_index: (step == 0).then_some(0),
+                        end_of_pass_write_index: (step + 1 >= steps).then_some(1),
+                    }
                 }),
             });
-            cpass.set_pipeline(&self.compute_pipeline);
-            cpass.set_bind_group(0, &self.bind_group, &[]);
-            cpass.set_bind_group(1, &self.particle_uniform.bind_group, &[]);
-            cpass.dispatch_workgroups((PARTICLE_POOLING as f32 / 64.0).ceil() as u32, 1, 1);
+            if step < steps {
+                for emitter in &mut self.emitters {
+                    emitter.simulate(&mut cpass, step);
+                }
+            }
         }
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -370,21 +413,68 @@ particle.position.z += particle.velocity * particle.dir.z * uniforms.delta_time;
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: Some(wgpu::RenderPassTimestampWrites {
-                    query_set: &profiler.timestamps.get(0).unwrap().query_timing,
-                    beginning_of_pass_write_index: Some(0),
-                    end_of_pass_write_index: Some(1),
+                timestamp_writes: profiler.timestamps.first().map(|query| {
+                    wgpu::RenderPassTimestampWrites {
+                        query_set: &query.query_timing,
+                        beginning_of_pass_write_index: Some(0),
+                        end_of_pass_write_index: Some(1),
+                    }
                 }),
                 occlusion_query_set: None,
             });
-            rpass.set_pipeline(&self.pipeline);
-            rpass.set_bind_group(0, &self.camera.uniform.bind_group, &[]);
-            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            rpass.set_vertex_buffer(1, self.particle_buffer.slice(..));
-            rpass.draw(0..6, 0..PARTICLE_POOLING as u32);
+            for emitter in &self.emitters {
+                emitter.draw(
+                    &mut rpass,
+                    &self.camera.uniform.bind_group,
+                    &self.vertex_buffer,
+                );
+            }
+        }
+        for emitter in &self.emitters {
+            emitter.copy_stats(encoder);
+        }
+        if self.bloom.enabled {
+            self.bloom.render(encoder, &self.view);
+            self.blend(encoder, &self.view, &ctx_view, &self.blend_pipeline);
+        } else {
+            self.blend(encoder, &self.view, &ctx_view, &self.copy_pipeline);
+        }
+    }
+
+    /// Changes the number of particles of the `emitter`-th emitter and returns the capacity
+    /// applied, see [`Emitter::set_capacity`].
+    pub fn set_capacity(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        emitter: usize,
+        capacity: u32,
+    ) -> Result<u32, FieldError> {
+        let scene = SceneBindings {
+            simulation: &self.simulation_buffer,
+            camera: &self.camera.uniform.buffer,
+            depth: &self.depth_view,
+        };
+        self.emitters[emitter]
+            .set_capacity(device, queue, capacity, &scene)
+            .map_err(|error| error.prefixed(&format!("emitters[{emitter}].")))
+    }
+
+    /// Reads back the number of live particles of every emitter, call it after the frame was
+    /// submitted. Without `wait` the gpu is only polled, the counts of a frame arrive with one of
+    /// the next frames.
+    pub fn read_stats(&mut self, device: &wgpu::Device, wait: bool) {
+        for emitter in &mut self.emitters {
+            emitter.map_stats();
+        }
+        let maintain = match wait {
+            true => wgpu::MaintainBase::wait(),
+            false => wgpu::MaintainBase::Poll,
+        };
+        device.poll(maintain).panic_on_timeout();
+        for emitter in &mut self.emitters {
+            emitter.read_stats();
         }
-        // self.bloom.render(encoder, &self.view);
-        self.blend(encoder, &self.view, &ctx_view, &self.blend_pipeline);
     }
 
     pub(self) fn blend(
@@ -417,12 +507,43 @@ particle.position.z += particle.velocity * particle.dir.z * uniforms.delta_time;
     }
 }
 
+/// The resources of the [`System`] bound at **@group(0)** of the compute pipelines of every
+/// emitter.
+pub struct SceneBindings<'a> {
+    /// the [`SimulationParams`] of the step, at **@binding(1)**.
+    pub simulation: &'a wgpu::Buffer,
+    /// the `Camera3DUniform`, at **@binding(9)** with a `Depth` collider.
+    pub camera: &'a wgpu::Buffer,
+    /// the depth prepass, at **@binding(10)** with a `Depth` collider.
+    pub depth: &'a wgpu::TextureView,
+}
+
+/// The default limits with the largest buffers the `adapter` allows, the particle buffers of an
+/// emitter grow with its capacity. The emitters with a grid or a fluid need more storage buffers
+/// than the default too.
+pub fn required_limits(adapter: &wgpu::Adapter) -> wgpu::Limits {
+    let limits = adapter.limits();
+    wgpu::Limits {
+        max_buffer_size: limits.max_buffer_size,
+        max_storage_buffer_binding_size: limits.max_storage_buffer_binding_size,
+        max_storage_buffers_per_shader_stage: limits.max_storage_buffers_per_shader_stage,
+        ..wgpu::Limits::default()
+    }
+}
+
 pub fn create_render_pipeline(
     device: &wgpu::Device,
     shader: &wgpu::ShaderModule,
     format: wgpu::TextureFormat,
+    blend: BlendMode,
     pipeline_layout: &wgpu::PipelineLayout,
 ) -> wgpu::RenderPipeline {
+    let dst_factor = match blend {
+        BlendMode::Additive => wgpu::BlendFactor::One,
+        BlendMode::Alpha => wgpu::BlendFactor::OneMinusSrcAlpha,
+    };
+
+    let attributes = Particle::vertex_attributes();
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: None,
         layout: Some(&pipeline_layout),
@@ -430,26 +551,7 @@ pub fn create_render_pipeline(
             module: &shader,
             entry_point: Some("vs_main"),
             compilation_options: Default::default(),
-            buffers: &[
-                Quad::desc(),
-                wgpu::VertexBufferLayout {
-                    array_stride: 16 * 4,
-                    step_mode: wgpu::VertexStepMode::Instance,
-                    attributes: &[
-                        //position
-                        wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x4,
-                            offset: 0,
-                            shader_location: 1,
-                        },
-                        wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x4,
-                            offset: 16,
-                            shader_location: 2,
-                        },
-                    ],
-                },
-            ],
+            buffers: &[Quad::desc(), Particle::vertex_layout(&attributes)],
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
@@ -460,7 +562,7 @@ pub fn create_render_pipeline(
                 blend: Some(wgpu::BlendState {
                     color: wgpu::BlendComponent {
                         src_factor: wgpu::BlendFactor::SrcAlpha,
-                        dst_factor: wgpu::BlendFactor::One,
+                        dst_factor,
                         operation: wgpu::BlendOperation::Add,
                     },
                     alpha: wgpu::BlendComponent {
@@ -480,22 +582,179 @@ pub fn create_render_pipeline(
     })
 }
 
-fn create_compute_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("Particle Bind Group Layout"),
-        entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
+/// The depth prepass is drawn by the occluders, the depth of the nearest one is also written to
+/// its `DEPTH_COPY_FORMAT` color target.
+pub fn create_depth_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    pipeline_layout: &wgpu::PipelineLayout,
+) -> wgpu::RenderPipeline {
+    let attributes = Particle::vertex_attributes();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Depth prepass pipeline"),
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[Quad::desc(), Particle::vertex_layout(&attributes)],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_depth"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: DEPTH_COPY_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::RED,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// The layout of the compute bindings of an emitter, `ping_pong` adds the read-only
+/// `particles_src` at **@binding(8)**, `depth_collision` adds the camera and the depth prepass
+/// at **@binding(9)** and **@binding(10)**, `grid` adds its tables from **@binding(11)** to
+/// **@binding(13)** and `fluid` adds its params and the densities at **@binding(14)** and
+/// **@binding(15)**.
+pub fn create_compute_bind_group_layout(
+    device: &wgpu::Device,
+    ping_pong: bool,
+    depth_collision: bool,
+    grid: bool,
+    fluid: bool,
+) -> wgpu::BindGroupLayout {
+    let mut entries = vec![
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 4,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 5,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 6,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 7,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+    ];
+    if ping_pong {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 8,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        });
+    }
+    if depth_collision {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 9,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        });
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 10,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Uint,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        });
+    }
+    if grid {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 11,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
             },
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
+            count: None,
+        });
+        for binding in [12, 13] {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding,
                 visibility: wgpu::ShaderStages::COMPUTE,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Storage { read_only: true },
@@ -503,21 +762,65 @@ fn create_compute_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLay
                     min_binding_size: None,
                 },
                 count: None,
+            });
+        }
+    }
+    if fluid {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 14,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
             },
-            wgpu::BindGroupLayoutEntry {
-                binding: 2,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
+            count: None,
+        });
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 15,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
             },
-        ],
+            count: None,
+        });
+    }
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Particle Bind Group Layout"),
+        entries: &entries,
     })
 }
 
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+/// The depth prepass as the compute shaders read it, the bits of the `f32` depth.
+const DEPTH_COPY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+/// length of [`System::step_once`] when the simulation does not use a fixed timestep.
+const SINGLE_STEP: instant::Duration = instant::Duration::from_nanos(16_666_667);
+pub const MIN_TIME_SCALE: f32 = 1.0 / 16.0;
+pub const MAX_TIME_SCALE: f32 = 16.0;
+
+/// `SimulationParams` of `vfx::particle`, the vectors are aligned to 16 bytes.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimulationParams {
+    delta_time: f32,
+    time: f32,
+    frame: u32,
+    seed: u32,
+    gravity: [f32; 3],
+    _pad0: f32,
+    wind: [f32; 3],
+    _pad1: f32,
+    bounds_min: [f32; 3],
+    _pad2: f32,
+    bounds_max: [f32; 3],
+    _pad3: f32,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 struct CameraPosition {
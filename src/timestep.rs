@@ -0,0 +1,86 @@
+use instant::Duration;
+
+/// Upper bound of [`FixedTimestep::max_substeps`], the per step uniforms are staged in buffers
+/// of this many entries.
+pub const MAX_SUBSTEPS: u32 = 16;
+
+/// Accumulates the frame time and splits it in steps of a fixed length, so the simulation does not
+/// depend on the frame rate.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    pub step: Duration,
+    /// steps simulated at most in a frame, the time left over after a stall is dropped instead of
+    /// catching up.
+    pub max_substeps: u32,
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration, max_substeps: u32) -> Self {
+        Self {
+            step,
+            max_substeps: max_substeps.clamp(1, MAX_SUBSTEPS),
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// Adds the frame time and returns how many steps must be simulated.
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        self.accumulator = (self.accumulator + dt).min(self.step * self.max_substeps);
+
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    /// How far the time is between the last two steps, used to interpolate the rendered
+    /// positions.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn steps_per_frame() {
+        let mut timestep = FixedTimestep::new(STEP, 4);
+        assert_eq!(timestep.advance(Duration::from_millis(10)), 1);
+        assert_eq!(timestep.advance(Duration::from_millis(30)), 3);
+        assert_eq!(timestep.advance(Duration::from_millis(5)), 0);
+        assert_eq!(timestep.alpha(), 0.5);
+    }
+
+    #[test]
+    fn remainder_carries_over() {
+        let mut timestep = FixedTimestep::new(STEP, 4);
+        // 61 frames of 16ms give 97 steps, the last 6ms stay in the accumulator.
+        let steps: u32 = (0..61)
+            .map(|_| timestep.advance(Duration::from_millis(16)))
+            .sum();
+        assert_eq!(steps, 97);
+        assert!((timestep.alpha() - 0.6).abs() < 1e-6);
+
+        assert_eq!(timestep.advance(Duration::from_millis(4)), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn long_stall_is_clamped() {
+        let mut timestep = FixedTimestep::new(STEP, 4);
+        assert_eq!(timestep.advance(Duration::from_secs(2)), 4);
+        // the time past the last step is dropped, the next frame starts over.
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(Duration::from_millis(10)), 1);
+
+        assert_eq!(FixedTimestep::new(STEP, 0).max_substeps, 1);
+        assert_eq!(FixedTimestep::new(STEP, 100).max_substeps, MAX_SUBSTEPS);
+    }
+}
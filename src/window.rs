@@ -8,7 +8,7 @@ use winit::{
     window::{Fullscreen, Window, WindowId},
 };
 
-use crate::{gpu::GpuState, window_proxy::Proxy};
+use crate::{emitter::SceneDesc, gpu::GpuState, window_proxy::Proxy};
 
 pub enum InputEvent<'a> {
     Window(&'a WindowEvent),
@@ -27,10 +27,10 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(event_loop: &EventLoop<GpuState>) -> Self {
+    pub fn new(event_loop: &EventLoop<GpuState>, desc: SceneDesc) -> Self {
         Self {
             time: instant::Instant::now(),
-            state: GpuStage::Wait(Proxy::new(event_loop.create_proxy())),
+            state: GpuStage::Wait(Proxy::new(event_loop.create_proxy(), desc)),
         }
     }
 }
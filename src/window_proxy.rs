@@ -4,16 +4,21 @@ use winit::{
     window::{Fullscreen, Window},
 };
 
+use crate::emitter::SceneDesc;
 use crate::gpu::GpuState;
 
 /// Proxy is intended to build (asyncronously on **WASM** and syncronously on **Native Platforms**) and send the gpu state with the window (that is stored in the GpuState) to the main event loop.
 pub struct Proxy {
     proxy: Option<EventLoopProxy<GpuState>>,
+    desc: SceneDesc,
 }
 
 impl Proxy {
-    pub fn new(proxy: EventLoopProxy<GpuState>) -> Self {
-        Self { proxy: Some(proxy) }
+    pub fn new(proxy: EventLoopProxy<GpuState>, desc: SceneDesc) -> Self {
+        Self {
+            proxy: Some(proxy),
+            desc,
+        }
     }
 
     pub fn send(&mut self, event_loop: &ActiveEventLoop) {
@@ -68,7 +73,7 @@ impl Proxy {
 
         #[cfg(target_arch = "wasm32")]
         {
-            let gpu = GpuState::new(window, size);
+            let gpu = GpuState::new(window, size, self.desc.clone());
             wasm_bindgen_futures::spawn_local(async move {
                 let gpu = gpu.await;
                 assert!(proxy.send_event(gpu).is_ok());
@@ -79,7 +84,7 @@ impl Proxy {
         {
             use pollster::FutureExt;
             assert!(proxy
-                .send_event(GpuState::new(window, size).block_on())
+                .send_event(GpuState::new(window, size, self.desc.clone()).block_on())
                 .is_ok());
         }
     }